@@ -0,0 +1,79 @@
+use std::io::Result;
+
+use moonlight::{
+    commands,
+    input::{InputEvent, Key},
+    BatchCmd, Cleanup,
+};
+
+// A prompt which asks to pick an item from a list and hands the choice back
+// to main once the program exits.
+
+const CHOICES: [&str; 4] = ["Bulbasaur", "Charmander", "Squirtle", "Pikachu"];
+
+#[derive(Clone)]
+struct Model {
+    cursor: usize,
+}
+
+#[derive(Clone)]
+enum Msg {
+    Up,
+    Down,
+    Choose,
+    Quit,
+}
+
+fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
+    match msg {
+        Msg::Up => Model {
+            cursor: model.cursor.saturating_sub(1),
+        },
+        Msg::Down => Model {
+            cursor: (model.cursor + 1).min(CHOICES.len() - 1),
+        },
+        // the chosen item is the exit value of the program
+        Msg::Choose => {
+            let choice = CHOICES[model.cursor];
+            return (model, vec![commands::quit_with(choice)]);
+        }
+        Msg::Quit => return (model, vec![commands::quit()]),
+    };
+    (model, vec![])
+}
+
+fn view(model: &Model) -> String {
+    let mut s = String::from("Choose your starter:\n\n");
+    for (i, choice) in CHOICES.iter().enumerate() {
+        let cursor = if i == model.cursor { ">" } else { " " };
+        s += &format!("{} {}\n", cursor, choice);
+    }
+    s + "\n(enter to choose, q to quit)"
+}
+
+fn input(event: InputEvent) -> Option<Msg> {
+    match event {
+        InputEvent::Key(key) => match key.key {
+            Key::Up | Key::Char('k') => Some(Msg::Up),
+            Key::Down | Key::Char('j') => Some(Msg::Down),
+            Key::Enter => Some(Msg::Choose),
+            Key::Char('q') | Key::Esc => Some(Msg::Quit),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn main() -> Result<()> {
+    let initialize = || (Model { cursor: 0 }, None);
+    // the prompt is erased, only the answer is left in the terminal
+    let exit = moonlight::Runtime::new(reducer, initialize, input, view)
+        .with_cleanup(Cleanup::Erase)
+        .run()?;
+
+    match exit.value::<&str>() {
+        Some(choice) => println!("You chose {}!", choice),
+        None => println!("Maybe next time."),
+    }
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use std::io::Result;
 
 use moonlight::{
-    heartbeat::Heartbeat,
+    commands,
     input::{InputEvent, Key},
     BatchCmd,
 };
@@ -25,15 +25,11 @@ enum Message {
 /// Reducer is called when messages are received. The idea is that you inspect
 /// the message and retun a the updated model.
 fn reducer(model: Model, message: Message) -> (Model, BatchCmd<Message>) {
-    let model = match message {
-        Message::Quit => {
-            Heartbeat::stop(); // kill runtime
-            Model(model.0)
-        }
-        Message::Increment => Model(model.0 + 1),
-        Message::Decrement => Model(model.0 - 1),
-    };
-    (model, vec![])
+    match message {
+        Message::Quit => (model, vec![commands::quit()]), // stop runtime
+        Message::Increment => (Model(model.0 + 1), vec![]),
+        Message::Decrement => (Model(model.0 - 1), vec![]),
+    }
 }
 
 /// View take data from the model and return a string which will be rendered
@@ -46,7 +42,7 @@ fn view(model: &Model) -> String {
 /// the event and returns an optional message.
 fn input(event: InputEvent) -> Option<Message> {
     match event {
-        InputEvent::Key(key) => match key {
+        InputEvent::Key(key) => match key.key {
             Key::Char('q') => Some(Message::Quit),
             Key::Char('k') => Some(Message::Increment),
             Key::Char('j') => Some(Message::Decrement),
@@ -58,5 +54,7 @@ fn input(event: InputEvent) -> Option<Message> {
 
 fn main() -> Result<()> {
     let initialize = || (Model(0), None);
-    moonlight::Runtime::new(reducer, initialize, input, view).run()
+    let exit = moonlight::Runtime::new(reducer, initialize, input, view).run()?;
+    println!("Final count: {}", exit.model.0);
+    Ok(())
 }
@@ -0,0 +1,71 @@
+use std::{env, io::Result, process::Command};
+
+use moonlight::{
+    commands,
+    input::{InputEvent, Key, KeyEvent, Modifiers},
+    BatchCmd,
+};
+
+// A program which hands the terminal over: to an editor, or to the shell
+// when suspended.
+
+#[derive(Clone)]
+struct Model {
+    status: String,
+}
+
+#[derive(Clone)]
+enum Msg {
+    OpenEditor,
+    EditorClosed(String),
+    Suspend,
+    Quit,
+}
+
+fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
+    match msg {
+        Msg::OpenEditor => {
+            let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+            let cmd = commands::exec(Command::new(editor), |status| {
+                Msg::EditorClosed(match status {
+                    Ok(status) => format!("editor exited with {}", status),
+                    Err(err) => format!("could not start the editor: {}", err),
+                })
+            });
+            (model, vec![cmd])
+        }
+        Msg::EditorClosed(status) => (Model { status }, vec![]),
+        Msg::Suspend => (model, vec![commands::suspend()]),
+        Msg::Quit => (model, vec![commands::quit()]),
+    }
+}
+
+fn view(model: &Model) -> String {
+    format!(
+        "{}\n\n(e to open $EDITOR, ctrl+z to suspend, q to quit)",
+        model.status
+    )
+}
+
+fn input(event: InputEvent) -> Option<Msg> {
+    match event {
+        InputEvent::Key(KeyEvent { key, modifiers }) => match (key, modifiers) {
+            (Key::Char('e'), _) => Some(Msg::OpenEditor),
+            (Key::Char('z'), Modifiers::CTRL) => Some(Msg::Suspend),
+            (Key::Char('q'), _) | (Key::Esc, _) => Some(Msg::Quit),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn main() -> Result<()> {
+    let initialize = || {
+        let model = Model {
+            status: String::from("Nothing edited yet."),
+        };
+        (model, None)
+    };
+    moonlight::Runtime::new(reducer, initialize, input, view).run()?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::io::Result;
+
+use moonlight::{
+    commands,
+    components::help,
+    input::{InputEvent, Key, KeyEvent, Modifiers},
+    keymap::{self, Binding},
+    BatchCmd,
+};
+
+// A program with its own key map, listed by the help component. Press ? to
+// switch between the short and the full help.
+
+#[derive(Clone)]
+struct KeyMap {
+    up: Binding,
+    down: Binding,
+    left: Binding,
+    right: Binding,
+    help: Binding,
+    quit: Binding,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let ctrl_c = KeyEvent::new(Key::Char('c'), Modifiers::CTRL);
+        Self {
+            up: Binding::new(vec![Key::Up.into(), Key::Char('k').into()])
+                .with_help("↑/k", "move up"),
+            down: Binding::new(vec![Key::Down.into(), Key::Char('j').into()])
+                .with_help("↓/j", "move down"),
+            left: Binding::new(vec![Key::Left.into(), Key::Char('h').into()])
+                .with_help("←/h", "move left"),
+            right: Binding::new(vec![Key::Right.into(), Key::Char('l').into()])
+                .with_help("→/l", "move right"),
+            help: Binding::new(vec![Key::Char('?').into()]).with_help("?", "toggle help"),
+            quit: Binding::new(vec![Key::Char('q').into(), Key::Esc.into(), ctrl_c])
+                .with_help("q", "quit"),
+        }
+    }
+}
+
+impl keymap::KeyMap for KeyMap {
+    fn short_help(&self) -> Vec<&Binding> {
+        vec![&self.help, &self.quit]
+    }
+
+    fn full_help(&self) -> Vec<Vec<&Binding>> {
+        vec![
+            vec![&self.up, &self.down, &self.left, &self.right],
+            vec![&self.help, &self.quit],
+        ]
+    }
+}
+
+#[derive(Clone)]
+struct Model {
+    keys: KeyMap,
+    help: help::Model,
+    last_key: String,
+}
+
+#[derive(Clone)]
+enum Msg {
+    Key(KeyEvent),
+    Resize(u16),
+}
+
+fn reducer(mut model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
+    let key = match msg {
+        Msg::Key(key) => key,
+        Msg::Resize(width) => {
+            model.help.width = width as usize;
+            return (model, vec![]);
+        }
+    };
+
+    let keys = &model.keys;
+    let pressed = if keys.up.matches(&key) {
+        "↑"
+    } else if keys.down.matches(&key) {
+        "↓"
+    } else if keys.left.matches(&key) {
+        "←"
+    } else if keys.right.matches(&key) {
+        "→"
+    } else if keys.help.matches(&key) {
+        model.help.show_all = !model.help.show_all;
+        return (model, vec![]);
+    } else if keys.quit.matches(&key) {
+        return (model, vec![commands::quit()]);
+    } else {
+        return (model, vec![]);
+    };
+    model.last_key = pressed.to_string();
+    (model, vec![])
+}
+
+fn view(model: &Model) -> String {
+    let status = match model.last_key.as_str() {
+        "" => String::from("Waiting for input..."),
+        key => format!("You chose: {}", key),
+    };
+    format!("\n{}\n\n\n\n{}", status, model.help.view(&model.keys))
+}
+
+fn input(event: InputEvent) -> Option<Msg> {
+    match event {
+        InputEvent::Key(key) => Some(Msg::Key(key)),
+        InputEvent::WindowSize { width, .. } => Some(Msg::Resize(width)),
+        _ => None,
+    }
+}
+
+fn main() -> Result<()> {
+    let initialize = || {
+        let model = Model {
+            keys: KeyMap::default(),
+            help: help::Model::new(),
+            last_key: String::new(),
+        };
+        (model, None)
+    };
+    moonlight::Runtime::new(reducer, initialize, input, view).run()?;
+    Ok(())
+}
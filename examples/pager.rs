@@ -1,16 +1,22 @@
 use std::fs;
 use std::io::Result;
 
-use moonlight::heartbeat::Heartbeat;
 use moonlight::{
+    border::Border,
+    commands,
     components::viewport::{self, Message},
-    input::{InputEvent, Key},
+    input::{InputEvent, Key, Modifiers, MouseAction, MouseButton},
+    layout::{Constraint, Layout, Rect},
+    style::{place, Align, Style, VerticalAlign},
     BatchCmd, Cmd,
 };
-use unicode_width::UnicodeWidthStr;
 
-const HEADER_HEIGHT: isize = 3;
-const FOOTER_HEIGHT: isize = 3;
+const HEADER_HEIGHT: u16 = 3;
+const FOOTER_HEIGHT: u16 = 3;
+
+// Draw the viewport through scroll regions, only the lines scrolling into
+// view are written. Needs fullscreen.
+const USE_HIGH_PERFORMANCE_RENDERER: bool = true;
 
 #[derive(Clone, Default)]
 struct Model {
@@ -36,33 +42,43 @@ impl From<viewport::Message> for Msg {
 
 fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
     let mut model = Model { ..model };
+    let mut cmds: BatchCmd<Msg> = vec![];
     match msg {
-        Msg::Quit => Heartbeat::stop(),
+        Msg::Quit => cmds.push(commands::quit()),
         Msg::WindowResized(w, h) => {
-            let (w, h) = (w as isize, h as isize);
-            let vertical_margins = HEADER_HEIGHT + FOOTER_HEIGHT;
+            let areas = Layout::vertical(vec![
+                Constraint::Length(HEADER_HEIGHT),
+                Constraint::Fill(1),
+                Constraint::Length(FOOTER_HEIGHT),
+            ])
+            .split(Rect::new(0, 0, w, h));
+            let body = areas[1];
 
             if !model.ready {
                 model.viewport = viewport::Model {
-                    width: w,
-                    height: h - vertical_margins,
+                    high_performance_rendering: USE_HIGH_PERFORMANCE_RENDERER,
                     ..Default::default()
                 };
-
-                model.viewport.y_position = HEADER_HEIGHT;
                 model.viewport.set_content(model.content.clone());
                 model.ready = true;
-            } else {
-                model.viewport.width = w;
-                model.viewport.height = h - vertical_margins;
+            }
+            model.viewport.y_position = body.y as isize;
+            model.viewport.width = body.width as isize;
+            model.viewport.height = body.height as isize;
+
+            // redraw the whole area, the terminal may have messed with it
+            if let Some(cmd) = viewport::sync(&model.viewport) {
+                cmds.push(commands::map(cmd));
             }
         }
         Msg::Viewport(msg) => {
-            viewport::update(msg, &mut model.viewport);
+            if let Some(cmd) = viewport::update(msg, &mut model.viewport) {
+                cmds.push(commands::map(cmd));
+            }
         }
     }
 
-    (model, vec![])
+    (model, cmds)
 }
 
 fn view(model: &Model) -> String {
@@ -70,25 +86,26 @@ fn view(model: &Model) -> String {
         return "\n Initializing...".to_string();
     }
 
-    let header_top = "╭───────────╮";
-    let mut header_mid = "│ Mr. Pager ├".to_string();
-    let header_bot = "╰───────────╯";
-    header_mid += &"─".repeat(
-        model.viewport.width as usize - UnicodeWidthStr::width(header_mid.as_str()) as usize,
+    let width = model.viewport.width as usize;
+    let header = Style::new()
+        .with_border(Border::rounded())
+        .with_border_title(" Mr. Pager ", Align::Left)
+        .with_padding(0, 1, 0, 1)
+        .with_width(width.saturating_sub(2))
+        .render("artichoke.md");
+
+    let percent = format!("{:.0}%", model.viewport.scroll_percent() * 100.0);
+    let footer = Style::new()
+        .with_border(Border::rounded())
+        .with_padding(0, 1, 0, 1)
+        .render(&percent);
+    let footer = place(
+        width,
+        FOOTER_HEIGHT as usize,
+        Align::Right,
+        VerticalAlign::Top,
+        &footer,
     );
-    let header = format!("{}\n{}\n{}", header_top, header_mid, header_bot);
-
-    let mut footer_top = "╭──────╮".to_string();
-    let mut footer_mid = format!("┤ {:.0}% │", model.viewport.scroll_percent() * 100.0);
-    let mut footer_bot = "╰──────╯".to_string();
-
-    let gap_size = model.viewport.width as usize - UnicodeWidthStr::width(footer_mid.as_str());
-
-    footer_top = " ".repeat(gap_size) + footer_top.as_str();
-    footer_mid = "─".repeat(gap_size) + footer_mid.as_str();
-    footer_bot = " ".repeat(gap_size) + footer_bot.as_str();
-
-    let footer = format!("{}\n{}\n{}", footer_top, footer_mid, footer_bot);
     format!(
         "{}\n{}\n{}",
         header,
@@ -99,9 +116,20 @@ fn view(model: &Model) -> String {
 
 fn input(event: InputEvent) -> Option<Msg> {
     match event {
-        InputEvent::Key(key) => match key {
-            Key::Char('q') | Key::Ctrl('c') | Key::Esc => Some(Msg::Quit),
-            key => Some(Msg::Viewport(Message::Input(key))),
+        InputEvent::Key(event) => match (event.key, event.modifiers) {
+            (Key::Char('q'), _) | (Key::Char('c'), Modifiers::CTRL) | (Key::Esc, _) => {
+                Some(Msg::Quit)
+            }
+            _ => Some(Msg::Viewport(Message::Input(event))),
+        },
+        InputEvent::Mouse(mouse) => match mouse.action {
+            MouseAction::Press(MouseButton::WheelUp) => {
+                Some(Msg::Viewport(Message::Input(Key::Up.into())))
+            }
+            MouseAction::Press(MouseButton::WheelDown) => {
+                Some(Msg::Viewport(Message::Input(Key::Down.into())))
+            }
+            _ => None,
         },
         InputEvent::WindowSize { width, height } => Some(Msg::WindowResized(width, height)),
         _ => None,
@@ -122,5 +150,17 @@ fn initialize() -> (Model, Option<Cmd<Msg>>) {
 }
 
 fn main() -> Result<()> {
-    moonlight::Runtime::new(reducer, initialize, input, view).run()
+    let mut runtime = moonlight::Runtime::new(reducer, initialize, input, view).with_mouse();
+
+    if USE_HIGH_PERFORMANCE_RENDERER {
+        runtime = runtime
+            .with_fullscreen()
+            .with_render_messages(|msg| match msg {
+                Msg::Viewport(msg) => viewport::render_msg(msg),
+                _ => None,
+            });
+    }
+
+    runtime.run()?;
+    Ok(())
 }
@@ -1,9 +1,9 @@
 use std::io::Result;
 
 use moonlight::{
+    commands,
     components::paginator::{self, PaginatorType},
-    heartbeat::Heartbeat,
-    input::{InputEvent, Key},
+    input::{InputEvent, Key, KeyEvent},
     BatchCmd, Cmd,
 };
 
@@ -20,7 +20,7 @@ struct Model {
 /// particular one pass input data to paginator model
 #[derive(Clone)]
 enum Msg {
-    Input(Key),
+    Input(KeyEvent),
     Quit,
 }
 
@@ -30,9 +30,9 @@ fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
     let mut model = Model { ..model };
 
     match msg {
-        Msg::Quit => Heartbeat::stop(),
+        Msg::Quit => return (model, vec![commands::quit()]),
         Msg::Input(key) => {
-            match key {
+            match key.key {
                 Key::Char('a') => model.paginator.paginator_type(PaginatorType::Arabic),
                 Key::Char('d') => model.paginator.paginator_type(PaginatorType::Dots),
                 _ => {}
@@ -53,9 +53,9 @@ fn view(model: &Model) -> String {
 /// the event and returns an optional message.
 fn input(event: InputEvent) -> Option<Msg> {
     match event {
-        InputEvent::Key(event) => match event {
+        InputEvent::Key(event) => match event.key {
             Key::Char('q') => Some(Msg::Quit),
-            key => Some(Msg::Input(key)),
+            _ => Some(Msg::Input(event)),
         },
         _ => None,
     }
@@ -70,5 +70,6 @@ fn initialize() -> (Model, Option<Cmd<Msg>>) {
 fn main() -> Result<()> {
     moonlight::Runtime::new(reducer, initialize, input, view)
         .with_fullscreen()
-        .run()
+        .run()?;
+    Ok(())
 }
@@ -1,8 +1,12 @@
 use std::io::Result;
-use std::{thread, time::Duration};
+use std::time::Duration;
 
-use moonlight::heartbeat::Heartbeat;
-use moonlight::{input::InputEvent, BatchCmd, Cmd};
+use moonlight::{
+    commands,
+    input::InputEvent,
+    subscriptions::{self, Sub},
+    BatchCmd, Cmd,
+};
 
 pub fn ease_out_bounce(t: f64) -> f64 {
     if t < 4. / 11.0 {
@@ -26,8 +30,6 @@ struct Model {
 
 impl Model {
     fn update(self, msg: Msg) -> Self {
-        
-
         match msg {
             Msg::Frame => {
                 let mut model = Model { ..self }; // deep copy
@@ -47,9 +49,6 @@ impl Model {
                 let mut model = Model { ..self }; // deepy copy
                 if model.loaded {
                     model.ticks -= 1;
-                    if model.ticks == 0 {
-                        Heartbeat::stop();
-                    }
                 }
                 model
             }
@@ -72,7 +71,11 @@ enum Msg {
     Tick,
 }
 fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
-    (model.update(msg), vec![])
+    let model = model.update(msg);
+    if model.loaded && model.ticks == 0 {
+        return (model, vec![commands::quit()]);
+    }
+    (model, vec![])
 }
 
 fn view(model: &Model) -> String {
@@ -99,14 +102,19 @@ fn progressbar(width: i32, percent: f64) -> String {
     );
 }
 
-fn frame(_: &Model) -> Msg {
-    thread::sleep(Duration::from_millis(16));
-    Msg::Frame
-}
-
-fn tick(_: &Model) -> Msg {
-    thread::sleep(Duration::from_secs(1));
-    Msg::Tick
+// Animate the bar while downloading, then count down once it's loaded.
+fn subscriptions(model: &Model) -> Vec<Sub<Msg>> {
+    if model.loaded {
+        vec![subscriptions::every("tick", Duration::from_secs(1), |_| {
+            Msg::Tick
+        })]
+    } else {
+        vec![subscriptions::every(
+            "frame",
+            Duration::from_millis(16),
+            |_| Msg::Frame,
+        )]
+    }
 }
 
 fn initialize() -> (Model, Option<Cmd<Msg>>) {
@@ -122,7 +130,7 @@ fn initialize() -> (Model, Option<Cmd<Msg>>) {
 fn main() -> Result<()> {
     moonlight::Runtime::new(reducer, initialize, input, view)
         .with_fullscreen()
-        .with_subscription(tick)
-        .with_subscription(frame)
-        .run()
+        .with_subscriptions(subscriptions)
+        .run()?;
+    Ok(())
 }
@@ -1,6 +1,11 @@
-use std::{io::Result, thread, time::Duration};
+use std::{io::Result, time::Duration};
 
-use moonlight::{heartbeat::Heartbeat, input::InputEvent, BatchCmd};
+use moonlight::{
+    commands,
+    input::InputEvent,
+    subscriptions::{self, Sub},
+    BatchCmd,
+};
 
 /// A simple program that counts down from 5 and then exits.
 
@@ -22,11 +27,11 @@ enum Msg {
 fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
     let mut model = Model(model.0);
     match msg {
-        Msg::Key => Heartbeat::stop(),
+        Msg::Key => return (model, vec![commands::quit()]),
         Msg::Tick => {
             model.0 -= 1;
             if model.0 <= 0 {
-                Heartbeat::stop()
+                return (model, vec![commands::quit()]);
             }
         }
     }
@@ -51,16 +56,18 @@ fn input(event: InputEvent) -> Option<Msg> {
     }
 }
 
-// This is a subscription which we setup on runtime. It waits for one
-// second, sends a tick, and then restart.
-fn tick(_: &Model) -> Msg {
-    thread::sleep(Duration::from_secs(1));
-    Msg::Tick
+// Subscriptions are computed from the model after every update, this one
+// sends a tick every second for as long as the program runs.
+fn subscriptions(_: &Model) -> Vec<Sub<Msg>> {
+    vec![subscriptions::every("tick", Duration::from_secs(1), |_| {
+        Msg::Tick
+    })]
 }
 
 fn main() -> Result<()> {
     let initialize = || (Model(5), None);
     moonlight::Runtime::new(reducer, initialize, input, view)
-        .with_subscription(tick)
-        .run()
+        .with_subscriptions(subscriptions)
+        .run()?;
+    Ok(())
 }
@@ -3,9 +3,8 @@ use std::io::Result;
 use moonlight::{
     commands,
     components::spinner::{self, SpinnerType, TickMsg},
-    heartbeat::Heartbeat,
     input::{InputEvent, Key},
-    Cmd,
+    BatchCmd, Cmd,
 };
 
 /// A simple program demonstrating the spinner component from the Moonlight
@@ -14,11 +13,13 @@ use moonlight::{
 #[derive(Clone)]
 struct Model {
     spinner: spinner::Model,
+    paused: bool,
 }
 
 #[derive(Clone)]
 enum Msg {
     SpinnerTick(TickMsg),
+    Focus(bool),
     Quit,
 }
 
@@ -28,17 +29,28 @@ impl From<TickMsg> for Msg {
     }
 }
 
-fn reducer(model: Model, msg: Msg) -> (Model, Vec<impl Fn() -> Msg>) {
+fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
     let mut model = Model { ..model };
 
     match msg {
-        Msg::Quit => Heartbeat::stop(),
+        Msg::Quit => (model, vec![commands::quit()]),
+        // the spinner stops while the terminal window isn't focused, dropping
+        // a tick ends its chain of ticks
+        Msg::SpinnerTick(_) if model.paused => (model, vec![]),
+        Msg::Focus(focused) => {
+            let paused = model.paused;
+            model.paused = !focused;
+            if paused && focused {
+                let restart = || Msg::from(spinner::tick());
+                return (model, vec![Cmd::new(restart)]);
+            }
+            (model, vec![])
+        }
         Msg::SpinnerTick(msg) => {
             let cmds = spinner::reducer(&mut model.spinner, msg);
-            return (model, commands::map_batch(cmds));
+            (model, commands::map_batch(cmds))
         }
     }
-    (model, vec![])
 }
 
 fn view(model: &Model) -> String {
@@ -48,10 +60,12 @@ fn view(model: &Model) -> String {
 
 fn input(event: InputEvent) -> Option<Msg> {
     match event {
-        InputEvent::Key(key) => match key {
+        InputEvent::Key(key) => match key.key {
             Key::Char('q') => Some(Msg::Quit),
             _ => None,
         },
+        InputEvent::FocusGained => Some(Msg::Focus(true)),
+        InputEvent::FocusLost => Some(Msg::Focus(false)),
         _ => None,
     }
 }
@@ -59,11 +73,13 @@ fn input(event: InputEvent) -> Option<Msg> {
 fn initialize() -> (Model, Option<Cmd<Msg>>) {
     let model = Model {
         spinner: spinner::Model::with(SpinnerType::MiniDot),
+        paused: false,
     };
     let ignition = || Msg::from(spinner::tick());
-    (model, Some(Box::new(ignition)))
+    (model, Some(Cmd::new(ignition)))
 }
 
 fn main() -> Result<()> {
-    moonlight::Runtime::new(reducer, initialize, input, view).run()
+    moonlight::Runtime::new(reducer, initialize, input, view).run()?;
+    Ok(())
 }
@@ -1,6 +1,8 @@
 use std::io::Result;
 
-use moonlight::{components::textinput, heartbeat::Heartbeat, input::InputEvent, BatchCmd, Key};
+use moonlight::{
+    commands, components::textinput, input::InputEvent, subscriptions::Sub, BatchCmd, Key,
+};
 
 #[derive(Clone)]
 struct Model {
@@ -29,7 +31,7 @@ enum Msg {
 fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
     let mut model = Model { ..model };
     match msg {
-        Msg::Quit => Heartbeat::stop(),
+        Msg::Quit => return (model, vec![commands::quit()]),
         Msg::InputMsg(input_msg) => model.input.reducer(input_msg),
     }
     (model, vec![])
@@ -46,21 +48,30 @@ fn view(model: &Model) -> String {
 
 fn input(event: InputEvent) -> Option<Msg> {
     match event {
-        InputEvent::Key(key) => match key {
+        InputEvent::Key(key) => match key.key {
             Key::Esc => Some(Msg::Quit),
             _ => Some(Msg::InputMsg(textinput::Message::Key(key))),
         },
+        InputEvent::Paste(text) => Some(Msg::InputMsg(textinput::Message::Paste(text))),
+        InputEvent::FocusGained => Some(Msg::InputMsg(textinput::Message::FocusGained)),
+        InputEvent::FocusLost => Some(Msg::InputMsg(textinput::Message::FocusLost)),
         _ => None,
     }
 }
 
-fn blink(model: &Model) -> Msg {
-    Msg::InputMsg(model.input.blink())
+fn subscriptions(model: &Model) -> Vec<Sub<Msg>> {
+    model
+        .input
+        .subscriptions()
+        .into_iter()
+        .map(|sub| sub.map(Msg::InputMsg))
+        .collect()
 }
 
 fn main() -> Result<()> {
     let initialize = || (Model::new(), None);
     moonlight::Runtime::new(reducer, initialize, input, view)
-        .with_subscription(blink)
-        .run()
+        .with_subscriptions(subscriptions)
+        .run()?;
+    Ok(())
 }
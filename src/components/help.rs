@@ -0,0 +1,239 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    keymap::{Binding, KeyMap},
+    style::Style,
+};
+
+const KEY_COLOR: &str = "#909090";
+const DESC_COLOR: &str = "#626262";
+const SEPARATOR_COLOR: &str = "#4A4A4A";
+
+/// Model renders the help of a key map: a short help on a single line, or the
+/// full help with a column per group of bindings. Disabled bindings and
+/// bindings without help are left out.
+#[derive(Clone)]
+pub struct Model {
+    /// show_all switches from the short help to the full help.
+    pub show_all: bool,
+    /// Width available for the help, what doesn't fit is replaced with the
+    /// ellipsis. Zero means no limit.
+    pub width: usize,
+    pub short_separator: String,
+    pub full_separator: String,
+    pub ellipsis: String,
+    pub key_style: Style,
+    pub desc_style: Style,
+    pub separator_style: Style,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Model {
+    pub fn new() -> Self {
+        Self {
+            show_all: false,
+            width: 0,
+            short_separator: String::from(" • "),
+            full_separator: String::from("    "),
+            ellipsis: String::from("…"),
+            key_style: Style::new().with_foreground(KEY_COLOR),
+            desc_style: Style::new().with_foreground(DESC_COLOR),
+            separator_style: Style::new().with_foreground(SEPARATOR_COLOR),
+        }
+    }
+
+    /// view renders the short or the full help, see show_all.
+    pub fn view(&self, key_map: &impl KeyMap) -> String {
+        if self.show_all {
+            self.full_help_view(&key_map.full_help())
+        } else {
+            self.short_help_view(&key_map.short_help())
+        }
+    }
+
+    /// short_help_view renders the bindings on a single line.
+    pub fn short_help_view(&self, bindings: &[&Binding]) -> String {
+        let mut out = String::new();
+        let mut total = 0;
+
+        for (i, binding) in shown(bindings).enumerate() {
+            let separator = if i > 0 { &self.short_separator[..] } else { "" };
+            let help = binding.help();
+            let width = separator.width() + help.key.width() + 1 + help.desc.width();
+
+            if self.width > 0 && total + width > self.width {
+                out += &self.ellipsis_view(total);
+                break;
+            }
+            total += width;
+            out += &self.separator_style.render(separator);
+            out += &self.key_style.render(&help.key);
+            out += " ";
+            out += &self.desc_style.render(&help.desc);
+        }
+
+        out
+    }
+
+    /// full_help_view renders each group of bindings as a column, keys and
+    /// descriptions are aligned.
+    pub fn full_help_view(&self, groups: &[Vec<&Binding>]) -> String {
+        let mut columns: Vec<(usize, Vec<String>)> = vec![];
+        let mut total = 0;
+
+        for group in groups {
+            let bindings: Vec<&Binding> = shown(group).collect();
+            if bindings.is_empty() {
+                continue;
+            }
+
+            let key_width = bindings.iter().map(|b| b.help().key.width()).max();
+            let key_width = key_width.unwrap_or(0);
+            let desc_width = bindings.iter().map(|b| b.help().desc.width()).max();
+            let width = key_width + 1 + desc_width.unwrap_or(0);
+            let separator = match columns.len() {
+                0 => 0,
+                _ => self.full_separator.width(),
+            };
+
+            if self.width > 0 && total + separator + width > self.width {
+                let ellipsis = self.ellipsis_view(total);
+                if !ellipsis.is_empty() {
+                    columns.push((0, vec![ellipsis]));
+                }
+                break;
+            }
+            total += separator + width;
+
+            // pad every line of the column to the same width
+            let lines = bindings
+                .iter()
+                .map(|binding| {
+                    let help = binding.help();
+                    let key_padding = " ".repeat(key_width - help.key.width());
+                    let padding = " ".repeat(width - key_width - 1 - help.desc.width());
+                    self.key_style.render(&help.key)
+                        + &key_padding
+                        + " "
+                        + &self.desc_style.render(&help.desc)
+                        + &padding
+                })
+                .collect();
+            columns.push((width, lines));
+        }
+
+        join_columns(&columns, &self.full_separator)
+    }
+
+    // The ellipsis with a space before it, if it fits in the width left.
+    fn ellipsis_view(&self, total: usize) -> String {
+        let ellipsis = format!(" {}", self.ellipsis);
+        if total + ellipsis.width() > self.width {
+            return String::new();
+        }
+        self.separator_style.render(&ellipsis)
+    }
+}
+
+// The bindings listed in the help.
+fn shown<'a>(bindings: &'a [&'a Binding]) -> impl Iterator<Item = &'a Binding> {
+    bindings
+        .iter()
+        .copied()
+        .filter(|binding| binding.enabled() && !binding.help().key.is_empty())
+}
+
+// Put columns of lines side by side, the lines of a column all have its width.
+fn join_columns(columns: &[(usize, Vec<String>)], separator: &str) -> String {
+    let rows = columns.iter().map(|(_, lines)| lines.len()).max();
+    (0..rows.unwrap_or(0))
+        .map(|row| {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|(width, lines)| match lines.get(row) {
+                    Some(line) => line.clone(),
+                    None => " ".repeat(*width),
+                })
+                .collect();
+            cells.join(separator).trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Key;
+
+    fn help(width: usize) -> Model {
+        Model {
+            width,
+            key_style: Style::new(),
+            desc_style: Style::new(),
+            separator_style: Style::new(),
+            ..Model::new()
+        }
+    }
+
+    fn binding(key: &str, desc: &str) -> Binding {
+        Binding::new(vec![Key::Enter.into()]).with_help(key, desc)
+    }
+
+    #[test]
+    fn renders_the_short_help() {
+        let (up, down, quit) = (
+            binding("↑/k", "up"),
+            binding("↓/j", "down"),
+            binding("q", "quit"),
+        );
+        let mut hidden = binding("x", "hidden");
+        hidden.set_enabled(false);
+        let no_help = Binding::new(vec![Key::Esc.into()]);
+        let bindings = [&up, &hidden, &down, &no_help, &quit];
+
+        assert_eq!(
+            help(0).short_help_view(&bindings),
+            "↑/k up • ↓/j down • q quit"
+        );
+        assert_eq!(
+            help(26).short_help_view(&bindings),
+            "↑/k up • ↓/j down • q quit"
+        );
+        // what doesn't fit is replaced with the ellipsis, if there's room
+        assert_eq!(help(20).short_help_view(&bindings), "↑/k up • ↓/j down …");
+        assert_eq!(help(18).short_help_view(&bindings), "↑/k up • ↓/j down");
+        assert_eq!(help(1).short_help_view(&bindings), "");
+    }
+
+    #[test]
+    fn renders_the_full_help() {
+        let (up, down, quit) = (
+            binding("↑/k", "up"),
+            binding("↓/j", "down"),
+            binding("q", "quit"),
+        );
+        let mut hidden = binding("x", "hidden");
+        hidden.set_enabled(false);
+
+        // the shorter column is padded under the longer one
+        let groups = vec![vec![&quit], vec![&up, &down], vec![&hidden]];
+        assert_eq!(
+            help(0).full_help_view(&groups),
+            "q quit    ↑/k up\n          ↓/j down"
+        );
+
+        let groups = vec![vec![&up, &down], vec![&quit]];
+        assert_eq!(
+            help(0).full_help_view(&groups),
+            "↑/k up      q quit\n↓/j down"
+        );
+        assert_eq!(help(15).full_help_view(&groups), "↑/k up       …\n↓/j down");
+        assert_eq!(help(9).full_help_view(&groups), "↑/k up\n↓/j down");
+    }
+}
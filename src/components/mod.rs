@@ -1,3 +1,4 @@
+pub mod help;
 pub mod paginator;
 pub mod spinner;
 pub mod textinput;
@@ -1,6 +1,8 @@
-use termion::color;
-
-use crate::{color::convert_hex_rgb, input::Key};
+use crate::{
+    input::{Key, KeyEvent},
+    keymap::{self, Binding},
+    style::Style,
+};
 
 /// paginator module provides a Moonlight module for calculating pagination and
 /// rendering pagination info. Note that this package does not render actual
@@ -14,6 +16,35 @@ pub enum PaginatorType {
     Dots,
 }
 
+/// KeyMap holds the bindings of the paginator, change them on the model to
+/// use other keys.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    pub prev_page: Binding,
+    pub next_page: Binding,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            prev_page: Binding::new(vec![Key::Left.into(), Key::Char('h').into()])
+                .with_help("←/h", "prev page"),
+            next_page: Binding::new(vec![Key::Right.into(), Key::Char('l').into()])
+                .with_help("→/l", "next page"),
+        }
+    }
+}
+
+impl keymap::KeyMap for KeyMap {
+    fn short_help(&self) -> Vec<&Binding> {
+        vec![&self.prev_page, &self.next_page]
+    }
+
+    fn full_help(&self) -> Vec<Vec<&Binding>> {
+        vec![self.short_help()]
+    }
+}
+
 const BRIGHT_GRAY: &str = "#DDDADA";
 const DARK_GRAY: &str = "#847A85";
 
@@ -26,10 +57,9 @@ pub struct Model {
     total_pages: i32,
     active_dot: String,
     inactive_dot: String,
-    use_left_right_keys: bool,
-    use_up_down_keys: bool,
-    use_h_l_keys: bool,
-    use_j_k_keys: bool,
+    pub active_dot_style: Style,
+    pub inactive_dot_style: Style,
+    pub key_map: KeyMap,
 }
 
 impl Default for Model {
@@ -46,16 +76,11 @@ impl Model {
             page: 0,
             per_page: 1,
             total_pages: 1,
-            active_dot: format!(
-                "{}{}",
-                "•",
-                color::Fg(convert_hex_rgb(BRIGHT_GRAY).unwrap()),
-            ),
-            inactive_dot: format!("{}{}", "•", color::Fg(convert_hex_rgb(DARK_GRAY).unwrap()),),
-            use_left_right_keys: true,
-            use_up_down_keys: false,
-            use_h_l_keys: true,
-            use_j_k_keys: false,
+            active_dot: String::from("•"),
+            inactive_dot: String::from("•"),
+            active_dot_style: Style::new().with_foreground(BRIGHT_GRAY),
+            inactive_dot_style: Style::new().with_foreground(DARK_GRAY),
+            key_map: KeyMap::default(),
         }
     }
 
@@ -128,38 +153,13 @@ impl Model {
     }
 }
 
-// input is the Moonlight input function which binds keystrokes to pagination
-pub fn input<MSG>(m: &mut Model, event: Key) -> Option<MSG> {
-    if m.use_left_right_keys {
-        match event {
-            Key::Left => m.prev_page(),
-            Key::Right => m.next_page(),
-            _ => return None,
-        }
-    }
-
-    if m.use_up_down_keys {
-        match event {
-            Key::Up => m.prev_page(),
-            Key::Down => m.next_page(),
-            _ => return None,
-        }
-    }
-
-    if m.use_h_l_keys {
-        match event {
-            Key::Char('h') => m.prev_page(),
-            Key::Char('l') => m.next_page(),
-            _ => return None,
-        }
-    }
-
-    if m.use_j_k_keys {
-        match event {
-            Key::Char('j') => m.prev_page(),
-            Key::Char('k') => m.next_page(),
-            _ => return None,
-        }
+// input is the Moonlight input function which binds keystrokes to pagination,
+// see KeyMap
+pub fn input<MSG>(m: &mut Model, event: KeyEvent) -> Option<MSG> {
+    if m.key_map.prev_page.matches(&event) {
+        m.prev_page();
+    } else if m.key_map.next_page.matches(&event) {
+        m.next_page();
     }
 
     None
@@ -178,10 +178,10 @@ fn dots_view(model: &Model) -> String {
 
     for i in 0..model.total_pages {
         if i == model.page {
-            s += model.active_dot.as_str();
+            s += &model.active_dot_style.render(&model.active_dot);
             continue;
         }
-        s += model.inactive_dot.as_str();
+        s += &model.inactive_dot_style.render(&model.inactive_dot);
     }
 
     s
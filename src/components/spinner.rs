@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use crate::commands;
+use crate::style::Style;
 use crate::BatchCmd;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -79,6 +80,8 @@ pub struct TickMsg {
 pub struct Model {
     /// Spinner settings to use. See type Spinner.
     spinner: Spinner,
+    /// Style of the frames.
+    pub style: Style,
     frame: usize,
     tag: i32,
 }
@@ -129,7 +132,7 @@ pub fn view(model: &Model) -> String {
         return "(error)".into();
     }
 
-    model.spinner.frames[model.frame].into()
+    model.style.render(model.spinner.frames[model.frame])
 }
 
 /// Tick is the command used to advance the spinner one frame. Use this command
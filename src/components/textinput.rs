@@ -1,13 +1,21 @@
-use std::{thread, time::Duration};
+use std::time::Duration;
 
-use termion::color;
+use crate::{
+    keymap::{self, Binding},
+    style::Style,
+    subscriptions::{self, Sub},
+    Cmd, Key, KeyEvent, Modifiers,
+};
 
-use crate::{convert_hex_rgb, renderer::invert, Cmd, Key};
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Message {
     CursorBlink,
-    Key(Key),
+    Key(KeyEvent),
+    Paste(String),
+    // the terminal window gained or lost focus, the cursor doesn't blink
+    // while it's unfocused
+    FocusGained,
+    FocusLost,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -24,6 +32,83 @@ pub enum CursorMode {
     Hide,
 }
 
+/// KeyMap holds the bindings used to edit the input, Emacs style by default.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    pub character_forward: Binding,
+    pub character_backward: Binding,
+    pub word_forward: Binding,
+    pub word_backward: Binding,
+    pub delete_character_backward: Binding,
+    pub delete_character_forward: Binding,
+    pub delete_after_cursor: Binding,
+    pub delete_before_cursor: Binding,
+    pub line_start: Binding,
+    pub line_end: Binding,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let ctrl = |key| KeyEvent::new(key, Modifiers::CTRL);
+        let alt = |key| KeyEvent::new(key, Modifiers::ALT);
+        Self {
+            character_forward: Binding::new(vec![Key::Right.into(), ctrl(Key::Char('f'))])
+                .with_help("→/ctrl+f", "forward"),
+            character_backward: Binding::new(vec![Key::Left.into(), ctrl(Key::Char('b'))])
+                .with_help("←/ctrl+b", "backward"),
+            word_forward: Binding::new(vec![
+                ctrl(Key::Right),
+                alt(Key::Right),
+                alt(Key::Char('f')),
+            ])
+            .with_help("ctrl+→/alt+f", "word forward"),
+            word_backward: Binding::new(vec![ctrl(Key::Left), alt(Key::Left), alt(Key::Char('b'))])
+                .with_help("ctrl+←/alt+b", "word backward"),
+            delete_character_backward: Binding::new(vec![Key::Backspace.into()])
+                .with_help("backspace", "delete"),
+            delete_character_forward: Binding::new(vec![Key::Delete.into(), ctrl(Key::Char('d'))])
+                .with_help("del/ctrl+d", "delete forward"),
+            delete_after_cursor: Binding::new(vec![ctrl(Key::Char('k'))])
+                .with_help("ctrl+k", "delete to end"),
+            delete_before_cursor: Binding::new(vec![ctrl(Key::Char('u'))])
+                .with_help("ctrl+u", "delete to start"),
+            line_start: Binding::new(vec![Key::Home.into(), ctrl(Key::Char('a'))])
+                .with_help("home/ctrl+a", "line start"),
+            line_end: Binding::new(vec![Key::End.into(), ctrl(Key::Char('e'))])
+                .with_help("end/ctrl+e", "line end"),
+        }
+    }
+}
+
+impl keymap::KeyMap for KeyMap {
+    fn short_help(&self) -> Vec<&Binding> {
+        vec![
+            &self.word_backward,
+            &self.word_forward,
+            &self.line_start,
+            &self.line_end,
+        ]
+    }
+
+    fn full_help(&self) -> Vec<Vec<&Binding>> {
+        vec![
+            vec![
+                &self.character_backward,
+                &self.character_forward,
+                &self.word_backward,
+                &self.word_forward,
+            ],
+            vec![&self.line_start, &self.line_end],
+            vec![
+                &self.delete_character_backward,
+                &self.delete_character_forward,
+                &self.delete_after_cursor,
+                &self.delete_before_cursor,
+            ],
+        ]
+    }
+}
+
 #[derive(Clone)]
 pub struct Model {
     pub prompt: String,
@@ -31,15 +116,18 @@ pub struct Model {
     pub blink_speed: Duration,
     pub value: String,
     pub cursor: String,
-    text_color: String,
-    placeholder_color: String,
-    cursor_color: String,
+    pub prompt_style: Style,
+    pub text_style: Style,
+    pub placeholder_style: Style,
+    pub cursor_style: Style,
     cusrsor_mode: CursorMode,
 
     pub char_limit: usize,
+    pub key_map: KeyMap,
     focus: bool,
     blink: bool,
     pos: usize,
+    window_focus: bool,
 }
 
 impl Model {
@@ -50,20 +138,31 @@ impl Model {
             cursor: String::from("\u{2588}"),
             blink_speed: Duration::from_millis(600),
             placeholder: String::new(),
-            text_color: String::new(),
-            placeholder_color: String::new(),
-            cursor_color: String::new(),
+            prompt_style: Style::new(),
+            text_style: Style::new(),
+            placeholder_style: Style::new(),
+            cursor_style: Style::new().with_reverse(),
             cusrsor_mode: CursorMode::Blink,
             char_limit: 0,
+            key_map: KeyMap::default(),
             focus: false,
             blink: true,
             pos: 0,
+            window_focus: true,
         }
     }
 
-    pub fn blink(&self) -> Message {
-        thread::sleep(self.blink_speed);
-        Message::CursorBlink
+    /// The cursor blinks while the cursor mode is `CursorMode::Blink` and the
+    /// terminal window is focused.
+    pub fn subscriptions(&self) -> Vec<Sub<Message>> {
+        if self.cusrsor_mode != CursorMode::Blink || !self.window_focus {
+            return vec![];
+        }
+
+        let key = format!("textinput/blink/{}", self.blink_speed.as_millis());
+        vec![subscriptions::every(key, self.blink_speed, |_| {
+            Message::CursorBlink
+        })]
     }
 
     pub fn focused(&self) -> bool {
@@ -87,27 +186,11 @@ impl Model {
     }
 
     fn color_text(&self, s: &str) -> String {
-        if self.text_color.is_empty() {
-            return String::from(s);
-        }
-
-        format!(
-            "{}{}",
-            s,
-            color::Fg(convert_hex_rgb(&self.text_color).unwrap())
-        )
+        self.text_style.render(s)
     }
 
     pub fn color_placeholder(&self, s: &str) -> String {
-        if self.placeholder_color.is_empty() {
-            return String::from(s);
-        }
-
-        format!(
-            "{}{}",
-            s,
-            color::Fg(convert_hex_rgb(&self.placeholder_color).unwrap())
-        )
+        self.placeholder_style.render(s)
     }
 
     pub fn view(&self) -> String {
@@ -117,14 +200,14 @@ impl Model {
 
         let mut v = self.color_text(&self.value[..self.pos]);
 
-        if self.pos < self.value.len() {
-            v += &cursor_view(&self.value.chars().nth(self.pos).unwrap().to_string(), self);
-            v += &self.color_text(&self.value[self.pos + 1..]);
+        if let Some(next) = self.next_char() {
+            v += &cursor_view(&self.value[self.pos..next], self);
+            v += &self.color_text(&self.value[next..]);
         } else {
             v += &cursor_view(" ", self);
         }
 
-        self.prompt.clone() + &v
+        self.prompt_style.render(&self.prompt) + &v
     }
 
     pub fn reducer(&mut self, msg: Message) {
@@ -134,75 +217,122 @@ impl Model {
 
         match msg {
             Message::CursorBlink => {
-                self.blink = !self.blink;
-            }
-            Message::Key(k) => match k {
-                Key::Backspace => {
-                    if !self.value.is_empty() {
-                        self.value = self.value[..max(0, self.pos - 1)].to_string()
-                            + &self.value[self.pos..];
-                        if self.pos > 0 {
-                            self.pos -= 1;
-                        }
-                    }
+                if self.window_focus {
+                    self.blink = !self.blink;
                 }
-                Key::Delete => {
-                    if self.value.is_empty() || self.pos >= self.value.len() {
-                        return;
-                    }
+            }
+            Message::FocusGained => self.window_focus = true,
+            Message::FocusLost => {
+                self.window_focus = false;
+                // keep the cursor visible while it stops blinking
+                self.blink = !self.focus;
+            }
+            Message::Key(event) => self.key(event),
+            Message::Paste(text) => self.paste(&text),
+        }
+    }
 
-                    self.value =
-                        self.value[..max(0, self.pos)].to_string() + &self.value[self.pos..];
-                }
-                Key::Left => {
-                    if self.pos > 0 {
-                        self.pos -= 1;
-                    }
-                }
-                Key::Right => {
-                    if self.pos < self.value.len() {
-                        self.pos += 1;
-                    }
-                }
-                Key::Ctrl('f') | Key::Ctrl('b') | Key::Ctrl('a') => {
-                    self.pos = 0;
-                }
-                Key::Ctrl('d') => {
-                    if !self.value.is_empty() && self.pos < self.value.len() {
-                        self.value =
-                            self.value[..self.pos].to_string() + &self.value[self.pos + 1..];
-                    }
-                }
-                Key::Ctrl('e') => {
-                    self.pos = self.value.len();
-                }
-                Key::Ctrl('k') => {
-                    self.value = self.value[..self.pos].to_string();
-                    self.pos = self.value.len();
-                }
-                Key::Ctrl('u') => {
-                    self.value = self.value[self.pos..].to_string();
-                    self.pos = 0;
-                }
-                Key::Char(c) => {
-                    if self.char_limit == 0 || self.value.len() < self.char_limit {
-                        self.value = self.value[..self.pos].to_string()
-                            + &c.to_string()
-                            + &self.value[self.pos..];
-                        self.pos += 1;
-                    }
-                }
-                _ => {}
-            },
+    // Edit the value or move the cursor following the key map, other
+    // characters are typed in.
+    fn key(&mut self, event: KeyEvent) {
+        let keys = &self.key_map;
+        if keys.delete_character_backward.matches(&event) {
+            if let Some(previous) = self.previous_char() {
+                self.value.remove(previous);
+                self.pos = previous;
+            }
+        } else if keys.delete_character_forward.matches(&event) {
+            if self.next_char().is_some() {
+                self.value.remove(self.pos);
+            }
+        } else if keys.delete_after_cursor.matches(&event) {
+            self.value.truncate(self.pos);
+        } else if keys.delete_before_cursor.matches(&event) {
+            self.value = self.value[self.pos..].to_string();
+            self.pos = 0;
+        } else if keys.word_backward.matches(&event) {
+            self.word_left();
+        } else if keys.word_forward.matches(&event) {
+            self.word_right();
+        } else if keys.character_backward.matches(&event) {
+            self.pos = self.previous_char().unwrap_or(self.pos);
+        } else if keys.character_forward.matches(&event) {
+            self.pos = self.next_char().unwrap_or(self.pos);
+        } else if keys.line_start.matches(&event) {
+            self.pos = 0;
+        } else if keys.line_end.matches(&event) {
+            self.pos = self.value.len();
+        } else if let Key::Char(c) = event.key {
+            // Shift is folded into the character
+            if (event.modifiers - Modifiers::SHIFT).is_empty()
+                && (self.char_limit == 0 || self.value.chars().count() < self.char_limit)
+            {
+                self.value.insert(self.pos, c);
+                self.pos += c.len_utf8();
+            }
         }
     }
 
+    // Insert pasted text at the cursor in one go. The input is a single line,
+    // so newlines and tabs become spaces, and whatever goes past the char
+    // limit is dropped.
+    fn paste(&mut self, text: &str) {
+        let available = match self.char_limit {
+            0 => usize::MAX,
+            limit => limit.saturating_sub(self.value.chars().count()),
+        };
+
+        let pasted: String = text
+            .chars()
+            .filter_map(|c| match c {
+                '\n' | '\t' => Some(' '),
+                c if c.is_control() => None,
+                c => Some(c),
+            })
+            .take(available)
+            .collect();
+
+        self.value.insert_str(self.pos, &pasted);
+        self.pos += pasted.len();
+    }
+
+    // The cursor is a byte index into the value, always on a character
+    // boundary. These are the positions of the characters around it.
+    fn previous_char(&self) -> Option<usize> {
+        self.value[..self.pos]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_char(&self) -> Option<usize> {
+        self.value[self.pos..]
+            .chars()
+            .next()
+            .map(|c| self.pos + c.len_utf8())
+    }
+
+    // Move the cursor to the start of the word before it.
+    fn word_left(&mut self) {
+        let before = self.value[..self.pos].trim_end_matches(' ');
+        self.pos = before.rfind(' ').map_or(0, |i| i + 1);
+    }
+
+    // Move the cursor to the end of the word after it.
+    fn word_right(&mut self) {
+        let after = &self.value[self.pos..];
+        let start = after.len() - after.trim_start_matches(' ').len();
+        self.pos += after[start..]
+            .find(' ')
+            .map_or(after.len(), |end| start + end);
+    }
+
     fn blink_cmd(&mut self) -> Option<Cmd<Message>> {
         if self.cusrsor_mode == CursorMode::Blink {
             return None;
         }
 
-        Some(Box::new(|| Message::CursorBlink))
+        Some(Cmd::new(|| Message::CursorBlink))
     }
 }
 
@@ -215,16 +345,18 @@ impl Default for Model {
 fn placeholder_view(model: &Model) -> String {
     let mut v = String::new();
     let p = &model.placeholder;
+    let first = p.chars().next().map_or(0, char::len_utf8);
+    let (head, tail) = p.split_at(first);
 
     if model.blink && !model.placeholder.is_empty() {
-        v += &cursor_view(&model.color_placeholder(&p[..1]), model);
+        v += &cursor_view(&model.color_placeholder(head), model);
     } else {
-        v += &cursor_view(&p[..1], model);
+        v += &cursor_view(head, model);
     }
 
-    v += &model.color_placeholder(&p[1..]);
+    v += &model.color_placeholder(tail);
 
-    model.prompt.clone() + &v
+    model.prompt_style.render(&model.prompt) + &v
 }
 
 fn cursor_view(s: &str, model: &Model) -> String {
@@ -232,13 +364,110 @@ fn cursor_view(s: &str, model: &Model) -> String {
         return s.to_string();
     }
 
-    invert(s.to_string())
+    model.cursor_style.render(s)
 }
 
-fn max(a: usize, b: usize) -> usize {
-    if a > b {
-        return a;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> Model {
+        let mut input = Model::new();
+        for c in text.chars() {
+            input.reducer(Message::Key(Key::Char(c).into()));
+        }
+        input
+    }
+
+    fn press(input: &mut Model, key: Key) {
+        input.reducer(Message::Key(key.into()));
+    }
+
+    #[test]
+    fn deletes_non_ascii_characters() {
+        let mut input = typed("café");
+        press(&mut input, Key::Backspace);
+        assert_eq!(input.value, "caf");
+
+        let mut input = typed("naïve");
+        press(&mut input, Key::Left);
+        press(&mut input, Key::Left);
+        press(&mut input, Key::Left);
+        press(&mut input, Key::Delete);
+        assert_eq!(input.value, "nave");
+        press(&mut input, Key::Backspace);
+        assert_eq!(input.value, "nve");
+    }
+
+    #[test]
+    fn moves_over_non_ascii_characters() {
+        let mut input = typed("日本");
+        press(&mut input, Key::Left);
+        press(&mut input, Key::Char('の'));
+        assert_eq!(input.value, "日の本");
+
+        press(&mut input, Key::Home);
+        press(&mut input, Key::Right);
+        press(&mut input, Key::Right);
+        press(&mut input, Key::Right);
+        press(&mut input, Key::Right);
+        press(&mut input, Key::Char('!'));
+        assert_eq!(input.value, "日の本!");
+    }
+
+    #[test]
+    fn limits_characters_not_bytes() {
+        let mut input = Model::new();
+        input.char_limit = 3;
+        for c in "éééé".chars() {
+            press(&mut input, Key::Char(c));
+        }
+        assert_eq!(input.value, "ééé");
+    }
+
+    #[test]
+    fn pastes_non_ascii_text() {
+        let mut input = typed("ab");
+        press(&mut input, Key::Left);
+        input.reducer(Message::Paste(String::from("héllo")));
+        assert_eq!(input.value, "ahéllob");
+        for _ in 0..4 {
+            press(&mut input, Key::Left);
+        }
+        input.prompt = String::new();
+        input.focus();
+        assert_eq!(input.view(), "ah\x1B[7mé\x1B[0mllob");
+        press(&mut input, Key::Backspace);
+        assert_eq!(input.value, "aéllob");
+
+        let mut input = Model::new();
+        input.char_limit = 3;
+        input.reducer(Message::Paste(String::from("éééé")));
+        assert_eq!(input.value, "ééé");
+    }
+
+    #[test]
+    fn shows_the_cursor_on_non_ascii_characters() {
+        let mut input = typed("ñu");
+        input.prompt = String::new();
+        press(&mut input, Key::Home);
+        assert_eq!(input.view(), "ñu");
+        press(&mut input, Key::End);
+        assert_eq!(input.view(), "ñu ");
+
+        // the cursor is drawn once focused
+        input.focus();
+        press(&mut input, Key::Home);
+        assert_eq!(input.view(), "\x1B[7mñ\x1B[0mu");
     }
 
-    b
+    #[test]
+    fn shows_a_non_ascii_placeholder() {
+        let mut input = Model::new();
+        input.prompt = String::new();
+        input.placeholder = String::from("ñame");
+        assert_eq!(input.view(), "ñame");
+        input.focus();
+        assert_eq!(input.view(), "\x1B[7mñ\x1B[0mame");
+    }
 }
@@ -1,12 +1,14 @@
 use crate::{
-    commands,
-    input::Key,
+    ansi, commands,
+    input::{Key, KeyEvent, Modifiers},
+    keymap::{self, Binding},
     renderer::{scroll_down, scroll_up, sync_scroll_area, RenderMsg},
+    Cmd,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Message {
-    Input(Key),
+    Input(KeyEvent),
     SyncScrollArea(RenderMsg),
     ScrollDown(RenderMsg),
     ScrollUp(RenderMsg),
@@ -22,13 +24,69 @@ impl From<RenderMsg> for Message {
     }
 }
 
+/// KeyMap holds the bindings used to scroll the viewport.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    pub page_down: Binding,
+    pub page_up: Binding,
+    pub half_page_down: Binding,
+    pub half_page_up: Binding,
+    pub down: Binding,
+    pub up: Binding,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let ctrl = |c| KeyEvent::new(Key::Char(c), Modifiers::CTRL);
+        Self {
+            page_down: Binding::new(vec![
+                Key::PageDown.into(),
+                Key::Char(' ').into(),
+                Key::Char('f').into(),
+            ])
+            .with_help("f/pgdn", "page down"),
+            page_up: Binding::new(vec![Key::PageUp.into(), Key::Char('b').into()])
+                .with_help("b/pgup", "page up"),
+            half_page_down: Binding::new(vec![Key::Char('d').into(), ctrl('d')])
+                .with_help("d", "½ page down"),
+            half_page_up: Binding::new(vec![Key::Char('u').into(), ctrl('u')])
+                .with_help("u", "½ page up"),
+            down: Binding::new(vec![Key::Down.into(), Key::Char('j').into()])
+                .with_help("↓/j", "down"),
+            up: Binding::new(vec![Key::Up.into(), Key::Char('k').into()]).with_help("↑/k", "up"),
+        }
+    }
+}
+
+impl keymap::KeyMap for KeyMap {
+    fn short_help(&self) -> Vec<&Binding> {
+        vec![&self.up, &self.down, &self.page_up, &self.page_down]
+    }
+
+    fn full_help(&self) -> Vec<Vec<&Binding>> {
+        vec![
+            vec![&self.up, &self.down],
+            vec![&self.page_up, &self.page_down],
+            vec![&self.half_page_up, &self.half_page_down],
+        ]
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Model {
     pub width: isize,
     pub height: isize,
     pub y_offset: isize,
+    /// Row of the screen where the viewport starts, only needed for
+    /// high-performance rendering.
     pub y_position: isize,
     pub lines: Vec<String>,
+    /// When enabled the viewport area is drawn by the renderer through
+    /// scroll regions instead of being part of the view. Requires
+    /// fullscreen and routing the render messages to the renderer with
+    /// `Runtime::with_render_messages` (see `render_msg`).
+    pub high_performance_rendering: bool,
+    pub key_map: KeyMap,
 }
 
 impl Model {
@@ -41,11 +99,11 @@ impl Model {
     }
 
     pub fn at_bottom(&self) -> bool {
-        self.y_offset >= self.len() - 1 - self.height
+        self.y_offset >= self.max_offset()
     }
 
     pub fn past_bottom(&self) -> bool {
-        self.y_offset > self.len() - 1 - self.height
+        self.y_offset > self.max_offset()
     }
 
     pub fn scroll_percent(&self) -> f64 {
@@ -54,17 +112,15 @@ impl Model {
         }
 
         let y = self.y_offset as f64;
-        let h = self.height as f64;
-        let t = (self.len() - 1) as f64;
-        let v = y / (t - h);
+        let v = y / self.max_offset() as f64;
         f64::max(0.0, f64::min(1.0, v))
     }
 
     pub fn set_content(&mut self, mut s: String) {
         s = s.replace("\r\n", "\n");
-        self.lines = s.split('\n').map(|s| s.to_string()).collect();
+        self.lines = ansi::lines(&s);
 
-        if self.y_offset > self.len() - 1 {
+        if self.past_bottom() {
             self.goto_bottom();
         }
     }
@@ -74,185 +130,137 @@ impl Model {
         if self.len() > 0 {
             let top = max(0, self.y_offset);
             let bottom = clamp(self.y_offset + self.height, top, self.len());
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
+            lines = self.slice(top, bottom);
         }
         lines
     }
 
-    fn view_down(&mut self) -> Option<Vec<String>> {
-        if self.at_bottom() {
-            return None;
+    // The lines from top to bottom, cut to the width of the viewport.
+    fn slice(&self, top: isize, bottom: isize) -> Vec<String> {
+        let lines = self.lines[(top as usize)..(bottom as usize)].iter();
+        if self.width <= 0 {
+            return lines.cloned().collect();
         }
+        lines
+            .map(|line| ansi::truncate(line, self.width as usize))
+            .collect()
+    }
 
-        self.y_offset = min(self.y_offset + self.height, self.len() - 1 - self.height);
-
-        Some(self.visible_lines())
+    fn view_down(&mut self) -> Option<Vec<String>> {
+        self.scroll_to(self.y_offset + self.height)
     }
 
     fn view_up(&mut self) -> Option<Vec<String>> {
-        if self.at_top() {
-            return None;
-        }
-
-        self.y_offset = max(self.y_offset - self.height, 0);
-
-        Some(self.visible_lines())
+        self.scroll_to(self.y_offset - self.height)
     }
 
     fn half_view_down(&mut self) -> Option<Vec<String>> {
-        let mut lines = vec![];
-
-        if self.at_bottom() {
-            return None;
-        }
-
-        self.y_offset = min(
-            self.y_offset + self.height / 2,
-            self.len() - 1 - self.height,
-        );
-
-        if self.len() > 0 {
-            let top = max(self.y_offset + self.height / 2, 0);
-            let bottom = clamp(self.y_offset + self.height, top, self.len() - 1);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
-        }
-
-        Some(lines)
+        self.scroll_to(self.y_offset + self.height / 2)
     }
 
     fn half_view_up(&mut self) -> Option<Vec<String>> {
-        let mut lines = vec![];
-
-        if self.at_top() {
-            return None;
-        }
-
-        self.y_offset = max(self.y_offset - self.height / 2, 0);
-
-        if self.len() > 0 {
-            let top = max(self.y_offset, 0);
-            let bottom = clamp(self.y_offset + self.height / 2, top, self.len() - 1);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
-        }
-
-        Some(lines)
+        self.scroll_to(self.y_offset - self.height / 2)
     }
 
-    fn line_down(&mut self, mut n: isize) -> Option<Vec<String>> {
-        let mut lines = vec![];
-
-        if self.at_bottom() || n == 0 {
-            return None;
-        }
-
-        let len = self.len();
-        let max_delta = (len - 1) - (self.y_offset + self.height);
-        n = min(n, max_delta);
-
-        self.y_offset = min(self.y_offset + n, len - 1 - self.height);
-
-        if len > 0 {
-            let top = max(self.y_offset + self.height - n, 0);
-            let bottom = clamp(self.y_offset + self.height, top, len - 1);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
-        }
-
-        Some(lines)
+    fn line_down(&mut self, n: isize) -> Option<Vec<String>> {
+        self.scroll_to(self.y_offset + n)
     }
 
-    fn line_up(&mut self, mut n: isize) -> Option<Vec<String>> {
-        let mut lines = vec![];
-
-        if self.at_top() || n == 0 {
-            return None;
-        }
-
-        n = min(n, self.y_offset);
-
-        self.y_offset = max(self.y_offset - n, 0);
-
-        let len = self.len();
-        if len > 0 {
-            let top = max(0, self.y_offset);
-            let bottom = clamp(self.y_offset + n, top, len - 1);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
-        }
-
-        Some(lines)
+    fn line_up(&mut self, n: isize) -> Option<Vec<String>> {
+        self.scroll_to(self.y_offset - n)
     }
 
     fn goto_top(&mut self) -> Option<Vec<String>> {
-        let mut lines = vec![];
-
-        if self.at_top() {
-            return None;
-        }
-
-        self.y_offset = 0;
-
-        if self.len() > 0 {
-            let top = self.y_offset;
-            let bottom = clamp(self.y_offset + self.height, top, self.len() - 1);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
-        }
-
-        Some(lines)
+        self.scroll_to(0)
     }
 
     fn goto_bottom(&mut self) -> Option<Vec<String>> {
-        let mut lines = vec![];
+        self.scroll_to(self.max_offset())
+    }
 
-        let len = self.len();
-        self.y_offset = max(len - 1 - self.height, 0);
+    // The offset showing the last line at the bottom of the viewport.
+    fn max_offset(&self) -> isize {
+        max(self.len() - self.height, 0)
+    }
 
-        if len > 0 {
-            let top = self.y_offset;
-            let bottom = max(len - 1, 0);
-            lines = self.lines[(top as usize)..(bottom as usize)].to_vec();
+    // Move the offset, kept between the top and the bottom, and return the
+    // lines which came into view: the renderer scrolls the area by as many
+    // lines. None when the offset didn't move.
+    fn scroll_to(&mut self, offset: isize) -> Option<Vec<String>> {
+        let old = self.y_offset;
+        let new = clamp(offset, 0, self.max_offset());
+        if new == old {
+            return None;
         }
-
-        Some(lines)
+        self.y_offset = new;
+
+        let bottom = min(new + self.height, self.len());
+        let (top, bottom) = if new > old {
+            (max(old + self.height, new), bottom)
+        } else {
+            (new, min(old, bottom))
+        };
+        Some(self.slice(top, max(top, bottom)))
     }
 }
 
-pub fn input(event: Key) -> Option<Message> {
+pub fn input(event: KeyEvent) -> Option<Message> {
     Some(Message::Input(event))
 }
 
-pub fn update(msg: Message, model: &mut Model) {
+/// update moves the viewport on key presses. With high-performance rendering
+/// it returns the command scrolling the area on screen.
+pub fn update(msg: Message, model: &mut Model) -> Option<Cmd<Message>> {
+    let key = match msg {
+        Message::Input(key) => key,
+        // render messages are for the renderer, see render_msg
+        _ => return None,
+    };
+
+    let (lines, down) = if model.key_map.page_down.matches(&key) {
+        (model.view_down(), true)
+    } else if model.key_map.page_up.matches(&key) {
+        (model.view_up(), false)
+    } else if model.key_map.half_page_down.matches(&key) {
+        (model.half_view_down(), true)
+    } else if model.key_map.half_page_up.matches(&key) {
+        (model.half_view_up(), false)
+    } else if model.key_map.down.matches(&key) {
+        (model.line_down(1), true)
+    } else if model.key_map.up.matches(&key) {
+        (model.line_up(1), false)
+    } else {
+        return None;
+    };
+
+    if !model.high_performance_rendering {
+        return None;
+    }
+
+    if down {
+        view_down(model, lines?)
+    } else {
+        view_up(model, lines?)
+    }
+}
+
+/// render_msg returns the message to hand to the renderer, if any. Meant to
+/// be used with `Runtime::with_render_messages`.
+pub fn render_msg(msg: &Message) -> Option<RenderMsg> {
     match msg {
-        Message::Input(key) => match key {
-            Key::PageDown | Key::Char(' ') | Key::Char('f') => {
-                model.view_down();
-            }
-            Key::PageUp | Key::Char('b') => {
-                model.view_up();
-            }
-            Key::Char('d') | Key::Ctrl('d') => {
-                model.half_view_down();
-            }
-            Key::Char('u') | Key::Ctrl('u') => {
-                model.half_view_up();
-            }
-            Key::Down | Key::Char('j') => {
-                model.line_down(1);
-            }
-            Key::Up | Key::Char('k') => {
-                model.line_up(1);
-            }
-            _ => {}
-        },
-        Message::ScrollUp(_) => {
-            model.line_up(1);
-        }
-        Message::ScrollDown(_) => {
-            model.line_down(1);
+        Message::SyncScrollArea(msg) | Message::ScrollDown(msg) | Message::ScrollUp(msg) => {
+            Some(msg.clone())
         }
-        _ => {}
+        Message::Input(_) => None,
     }
 }
 
 pub fn view(model: &Model) -> String {
+    // the renderer draws the area, leave it blank
+    if model.high_performance_rendering {
+        return vec![""; model.height.max(0) as usize].join("\n");
+    }
+
     let lines = model.visible_lines();
 
     let mut extra_lines = String::from("");
@@ -266,22 +274,21 @@ pub fn view(model: &Model) -> String {
 
 // COMMANDS
 
-pub fn sync(m: &Model) -> Option<impl Fn() -> Message> {
+/// sync tells the renderer to draw the whole viewport area, use it when the
+/// content changes or after the terminal is resized.
+pub fn sync(m: &Model) -> Option<Cmd<Message>> {
     if m.lines.is_empty() {
         return None;
     }
 
-    let top = m.y_offset.max(0) as usize;
-    let bottom = clamp(m.y_offset - m.height, 0, m.lines.len() as isize - 1);
-
     Some(commands::map(sync_scroll_area(
-        m.lines[(top as usize)..(bottom as usize)].to_vec(),
+        m.visible_lines(),
         m.y_position,
         m.y_position + m.height,
     )))
 }
 
-pub fn view_down(m: &Model, lines: Vec<String>) -> Option<impl Fn() -> Message> {
+pub fn view_down(m: &Model, lines: Vec<String>) -> Option<Cmd<Message>> {
     if lines.is_empty() {
         return None;
     }
@@ -293,7 +300,7 @@ pub fn view_down(m: &Model, lines: Vec<String>) -> Option<impl Fn() -> Message>
     )))
 }
 
-pub fn view_up(m: &Model, lines: Vec<String>) -> Option<impl Fn() -> Message> {
+pub fn view_up(m: &Model, lines: Vec<String>) -> Option<Cmd<Message>> {
     if lines.is_empty() {
         return None;
     }
@@ -324,3 +331,92 @@ fn max(a: isize, b: isize) -> isize {
 
     b
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Kind;
+
+    fn model(lines: usize, height: isize) -> Model {
+        let mut model = Model {
+            height,
+            high_performance_rendering: true,
+            ..Model::default()
+        };
+        let content: Vec<String> = (0..lines).map(|i| format!("line {}", i)).collect();
+        model.set_content(content.join("\n"));
+        model
+    }
+
+    // Scroll the lines on screen like the renderer does with the command.
+    fn apply(screen: &mut Vec<String>, height: usize, cmd: Cmd<Message>) {
+        let msg = match cmd.kind {
+            Kind::Ready(msg) => render_msg(&msg).unwrap(),
+            _ => panic!("expected a render message"),
+        };
+        match msg {
+            RenderMsg::ScrollDown { lines, .. } => {
+                let n = lines.len().min(height);
+                screen.drain(..n);
+                screen.extend_from_slice(&lines[lines.len() - n..]);
+            }
+            RenderMsg::ScrollUp { lines, .. } => {
+                let n = lines.len().min(height);
+                screen.splice(..0, lines[..n].iter().cloned());
+                screen.truncate(height);
+            }
+            RenderMsg::SyncScrollArea { .. } => panic!("unexpected sync"),
+        }
+    }
+
+    fn expected(model: &Model) -> String {
+        view(&Model {
+            high_performance_rendering: false,
+            ..model.clone()
+        })
+    }
+
+    #[test]
+    fn scrolls_the_lines_that_came_into_view() {
+        let mut model = model(100, 10);
+        let mut screen = model.visible_lines();
+        let keys = "jjdddddddddddjfkkubbbbbbbbbbbbbfffffffffffffffffuuujj";
+        for c in keys.chars() {
+            if let Some(cmd) = update(Message::Input(Key::Char(c).into()), &mut model) {
+                apply(&mut screen, 10, cmd);
+            }
+            assert_eq!(screen.join("\n"), expected(&model), "after {:?}", c);
+        }
+    }
+
+    #[test]
+    fn reaches_the_last_line() {
+        let mut model = model(100, 10);
+        model.goto_bottom();
+        assert_eq!(model.y_offset, 90);
+        assert!(model.at_bottom());
+        assert_eq!(model.visible_lines().last().unwrap(), "line 99");
+        assert!(model.line_down(1).is_none());
+
+        // the offset moves by 5, as many lines come into view
+        model.y_offset = 85;
+        let lines = model.half_view_down().unwrap();
+        assert_eq!(model.y_offset, 90);
+        assert_eq!(
+            lines,
+            ["line 95", "line 96", "line 97", "line 98", "line 99"]
+        );
+        let lines = model.view_up().unwrap();
+        assert_eq!(model.y_offset, 80);
+        assert_eq!(lines.len(), 10);
+    }
+
+    #[test]
+    fn stays_put_when_the_content_fits() {
+        let mut model = model(5, 10);
+        assert!(model.at_bottom());
+        assert!(model.view_down().is_none());
+        assert!(model.line_up(1).is_none());
+        assert_eq!(model.y_offset, 0);
+    }
+}
@@ -0,0 +1,315 @@
+use std::mem;
+
+use unicode_width::UnicodeWidthChar;
+
+const ESC: char = '\x1B';
+const RESET: &str = "\x1B[0m";
+
+/// Part of a string as the terminal sees it: an escape sequence, which takes
+/// no room on screen, or a character.
+pub(crate) enum Part<'a> {
+    Escape(&'a str),
+    Char(char),
+}
+
+/// parts splits a string into escape sequences and characters.
+pub(crate) fn parts(s: &str) -> impl Iterator<Item = Part<'_>> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let len = if c == ESC {
+            escape_len(rest)
+        } else {
+            c.len_utf8()
+        };
+        let (part, tail) = rest.split_at(len);
+        rest = tail;
+        Some(match c {
+            ESC => Part::Escape(part),
+            c => Part::Char(c),
+        })
+    })
+}
+
+// The length of the escape sequence at the start of s.
+fn escape_len(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next() {
+        // CSI, ends with a byte in the range 0x40-0x7E
+        Some((_, '[')) => chars
+            .find(|(_, c)| ('\x40'..='\x7E').contains(c))
+            .map_or(s.len(), |(i, _)| i + 1),
+        // OSC, ends with BEL or ST (ESC \)
+        Some((_, ']')) => {
+            let mut previous = ' ';
+            for (i, c) in chars {
+                if c == '\x07' || (previous == ESC && c == '\\') {
+                    return i + 1;
+                }
+                previous = c;
+            }
+            s.len()
+        }
+        Some((i, c)) => i + c.len_utf8(),
+        None => s.len(),
+    }
+}
+
+/// width is the display width of a line, escape sequences take no room.
+pub fn width(s: &str) -> usize {
+    parts(s)
+        .map(|part| match part {
+            Part::Char(c) => c.width().unwrap_or(0),
+            Part::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// truncate cuts a line to the given display width. Escape sequences don't
+/// take any room on screen, so they are kept, including the ones after the
+/// cut (e.g. a trailing reset).
+pub fn truncate(s: &str, width: usize) -> String {
+    truncate_with_tail(s, width, "")
+}
+
+/// truncate_with_tail cuts a line like truncate and puts the tail, e.g. an
+/// ellipsis, where it was cut. The tail counts in the width, lines that fit
+/// are left as they are.
+pub fn truncate_with_tail(s: &str, width: usize, tail: &str) -> String {
+    if self::width(s) <= width {
+        return s.to_string();
+    }
+    let tail = truncate(tail, width);
+    let width = width - self::width(&tail);
+
+    let mut out = String::with_capacity(s.len() + tail.len());
+    let mut used = 0;
+
+    for part in parts(s) {
+        match part {
+            Part::Escape(sequence) => out.push_str(sequence),
+            Part::Char(c) => {
+                let w = c.width().unwrap_or(0);
+                if used + w > width {
+                    // past the edge, only escape sequences are kept from now on
+                    if used <= width {
+                        out.push_str(&tail);
+                    }
+                    used = width + 1;
+                    continue;
+                }
+                used += w;
+                out.push(c);
+            }
+        }
+    }
+
+    out
+}
+
+/// lines splits text into lines. The styles still on at the end of a line
+/// are turned off there and on again at the start of the next one, so each
+/// line can be drawn on its own.
+pub fn lines(s: &str) -> Vec<String> {
+    hard_wrap(s, usize::MAX)
+}
+
+/// hard_wrap breaks text into lines of at most the given display width,
+/// cutting words if needed. Styles are carried over the breaks like with
+/// lines.
+pub fn hard_wrap(s: &str, width: usize) -> Vec<String> {
+    let mut out = Lines::new(width);
+    for part in parts(s) {
+        match part {
+            Part::Char('\n') => out.break_line(),
+            part => out.push(part),
+        }
+    }
+    out.lines
+}
+
+/// word_wrap breaks text into lines of at most the given display width, at
+/// the spaces between words. The spaces at a break are dropped, words longer
+/// than the width are cut. Styles are carried over the breaks like with
+/// lines.
+pub fn word_wrap(s: &str, width: usize) -> Vec<String> {
+    let mut out = Lines::new(width);
+    let mut lead: Vec<Part> = vec![]; // spaces and styles before the word
+    let mut word: Vec<Part> = vec![];
+    let mut word_width = 0;
+
+    for part in parts(s) {
+        match part {
+            Part::Char(' ') | Part::Escape(_) if word_width == 0 => lead.push(part),
+            Part::Char(' ') => {
+                out.push_word(mem::take(&mut lead), word.drain(..), word_width);
+                word_width = 0;
+                lead.push(part);
+            }
+            Part::Char('\n') => {
+                out.push_word(mem::take(&mut lead), word.drain(..), word_width);
+                out.break_line();
+                word_width = 0;
+            }
+            Part::Char(c) => {
+                word_width += c.width().unwrap_or(0);
+                word.push(part);
+            }
+            Part::Escape(_) => word.push(part),
+        }
+    }
+    out.push_word(lead, word.drain(..), word_width);
+
+    out.lines
+}
+
+// Lines being wrapped, with the styles that are on.
+struct Lines {
+    lines: Vec<String>,
+    styles: Vec<String>,
+    width: usize,
+    used: usize,
+}
+
+impl Lines {
+    fn new(width: usize) -> Self {
+        Self {
+            lines: vec![String::new()],
+            styles: vec![],
+            width: width.max(1),
+            used: 0,
+        }
+    }
+
+    fn line(&mut self) -> &mut String {
+        self.lines.last_mut().unwrap()
+    }
+
+    fn break_line(&mut self) {
+        if !self.styles.is_empty() {
+            self.line().push_str(RESET);
+        }
+        let styles = self.styles.concat();
+        self.lines.push(styles);
+        self.used = 0;
+    }
+
+    // Add a part, a character past the width goes on a new line.
+    fn push(&mut self, part: Part) {
+        match part {
+            Part::Escape(sequence) => {
+                match sgr(sequence) {
+                    Some("") | Some("0") => self.styles.clear(),
+                    Some(_) => self.styles.push(sequence.to_string()),
+                    None => {}
+                }
+                self.line().push_str(sequence);
+            }
+            Part::Char(c) => {
+                let w = c.width().unwrap_or(0);
+                if self.used + w > self.width && self.used > 0 {
+                    self.break_line();
+                }
+                self.used += w;
+                self.line().push(c);
+            }
+        }
+    }
+
+    // Add a word after the spaces and styles leading to it, on a new line if
+    // it doesn't fit. The spaces are dropped at the break, unless they indent
+    // the start of a line, and cut at the end of the line when there's no
+    // word.
+    fn push_word<'a>(
+        &mut self,
+        lead: Vec<Part<'a>>,
+        word: impl Iterator<Item = Part<'a>>,
+        width: usize,
+    ) {
+        let spaces = lead.iter().filter(|p| matches!(p, Part::Char(_))).count();
+        let room = self.width.saturating_sub(self.used);
+        let indent = self.used == 0 && spaces > 0;
+        let fits = width == 0 || spaces + width <= room || (self.used == 0 && spaces == 0);
+
+        let mut keep = spaces.min(room);
+        if !fits && !indent {
+            self.break_line();
+            keep = 0;
+        }
+        for part in lead {
+            match part {
+                Part::Char(_) if keep == 0 => {}
+                Part::Char(_) => {
+                    keep -= 1;
+                    self.push(part);
+                }
+                part => self.push(part),
+            }
+        }
+        if !fits && indent {
+            self.break_line();
+        }
+        for part in word {
+            self.push(part);
+        }
+    }
+}
+
+// The parameters of an SGR sequence, which sets the style of the text.
+fn sgr(sequence: &str) -> Option<&str> {
+    sequence.strip_prefix("\x1B[")?.strip_suffix('m')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_width_without_escapes() {
+        assert_eq!(width("\x1B[1;31mbold\x1B[0m"), 4);
+        assert_eq!(width("日本"), 4);
+        assert_eq!(width("\x1B]8;;https://example.com\x07link\x1B]8;;\x07"), 4);
+    }
+
+    #[test]
+    fn truncates_with_a_tail() {
+        assert_eq!(truncate("\x1B[31mhello\x1B[0m", 3), "\x1B[31mhel\x1B[0m");
+        assert_eq!(truncate_with_tail("hello", 5, "…"), "hello");
+        assert_eq!(truncate_with_tail("hello world", 6, "…"), "hello…");
+        assert_eq!(truncate_with_tail("日本語", 5, "…"), "日本…");
+        // the tail is cut too when it's wider than the line
+        assert_eq!(truncate_with_tail("hello", 2, "..."), "..");
+        assert_eq!(truncate_with_tail("hello", 0, "..."), "");
+    }
+
+    #[test]
+    fn hard_wraps_and_carries_styles() {
+        assert_eq!(
+            hard_wrap("\x1B[1mabcdef\x1B[0mgh", 3),
+            ["\x1B[1mabc\x1B[0m", "\x1B[1mdef\x1B[0m", "gh"]
+        );
+        assert_eq!(lines("a\n\nb"), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn word_wraps_and_carries_styles() {
+        assert_eq!(
+            word_wrap("the quick brown fox", 10),
+            ["the quick", "brown fox"]
+        );
+        assert_eq!(word_wrap("abcdefgh ij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(
+            word_wrap("\x1B[31mred words\x1B[0m here", 5),
+            ["\x1B[31mred\x1B[0m", "\x1B[31mwords\x1B[0m", "here"]
+        );
+    }
+
+    #[test]
+    fn word_wraps_indented_lines() {
+        assert_eq!(
+            word_wrap("\x1B[41m  hello world", 5),
+            ["\x1B[41m  \x1B[0m", "\x1B[41mhello\x1B[0m", "\x1B[41mworld"]
+        );
+        assert_eq!(word_wrap("  hi there", 5), ["  hi", "there"]);
+    }
+}
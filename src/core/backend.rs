@@ -0,0 +1,399 @@
+use std::{
+    io::{self, stdout, Read, Stdout, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex, MutexGuard, Once, TryLockError,
+    },
+    thread,
+    time::Duration,
+};
+
+use termion::raw::{IntoRawMode, RawTerminal};
+
+/// Backend is the terminal a program renders to and reads input from. The
+/// runtime uses the termion backend by default, pass another one with
+/// `Runtime::with_backend` to run a program over a pty, a socket or a buffer.
+///
+/// Backends must be `Send` so the terminal can be restored from the panic hook
+/// and signal handlers.
+pub trait Backend: Write + Send {
+    /// Size of the terminal as (columns, rows).
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    fn enable_raw_mode(&mut self) -> io::Result<()>;
+
+    fn disable_raw_mode(&mut self) -> io::Result<()>;
+
+    /// Source of the input bytes, they are parsed into `InputEvent`s on the
+    /// input thread. Called once when the program starts.
+    fn reader(&mut self) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Stop reading input while another process uses the terminal, see
+    /// `commands::exec`, until `resume_input` is called.
+    fn pause_input(&mut self) {}
+
+    fn resume_input(&mut self) {}
+
+    /// Whether the output is shown by a terminal, colors are only written to
+    /// one. The color profile follows it unless it was set with
+    /// `color::set_profile`.
+    fn is_terminal(&self) -> bool {
+        true
+    }
+}
+
+/// TermionBackend renders to stdout and reads from stdin, this is the default
+/// backend.
+pub struct TermionBackend {
+    stdout: RawTerminal<Stdout>,
+    reader: Option<usize>, // id of the StdinReader handed out
+}
+
+impl TermionBackend {
+    pub fn new() -> io::Result<Self> {
+        let stdout = stdout().into_raw_mode()?;
+        Ok(Self {
+            stdout,
+            reader: None,
+        })
+    }
+}
+
+// The program is done with the terminal, its input thread gets the end of
+// input so stdin is left alone for whoever reads it next.
+impl Drop for TermionBackend {
+    fn drop(&mut self) {
+        if let Some(id) = self.reader {
+            close_stdin_reader(id);
+        }
+    }
+}
+
+impl Write for TermionBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+impl Backend for TermionBackend {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        termion::terminal_size()
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        self.stdout.activate_raw_mode()
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        self.stdout.suspend_raw_mode()
+    }
+
+    fn is_terminal(&self) -> bool {
+        termion::is_tty(&stdout())
+    }
+
+    fn reader(&mut self) -> io::Result<Box<dyn Read + Send>> {
+        let reader = StdinReader::new();
+        self.reader = Some(reader.id);
+        Ok(Box::new(reader))
+    }
+
+    fn pause_input(&mut self) {
+        STDIN_PAUSED.store(true, Ordering::SeqCst);
+    }
+
+    fn resume_input(&mut self) {
+        STDIN_PAUSED.store(false, Ordering::SeqCst);
+    }
+}
+
+// Bytes read from stdin go to the newest StdinReader, the sender is replaced
+// every time one is created and removed once that reader is closed. Without
+// a reader stdin isn't read at all.
+static STDIN_ONCE: Once = Once::new();
+static STDIN_SENDER: Mutex<Option<(usize, Sender<Vec<u8>>)>> = Mutex::new(None);
+static STDIN_READERS: AtomicUsize = AtomicUsize::new(0);
+static STDIN_PAUSED: AtomicBool = AtomicBool::new(false);
+
+// how often a paused or idle stdin thread checks whether it's needed
+const STDIN_POLL: Duration = Duration::from_millis(50);
+
+/// StdinReader reads stdin through a thread shared by every runtime of the
+/// process. A blocked read on stdin can't be interrupted, so when a program
+/// exits its input thread would otherwise steal the first bytes meant for the
+/// next program.
+struct StdinReader {
+    id: usize,
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl StdinReader {
+    fn new() -> Self {
+        let id = STDIN_READERS.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = channel();
+        *STDIN_SENDER.lock().unwrap() = Some((id, sender));
+
+        STDIN_ONCE.call_once(|| {
+            thread::spawn(|| {
+                let mut buf = [0; 1024];
+                loop {
+                    if !stdin_wanted() {
+                        thread::sleep(STDIN_POLL);
+                        continue;
+                    }
+                    // input may have been paused or closed while waiting
+                    if !stdin_ready(STDIN_POLL) || !stdin_wanted() {
+                        continue;
+                    }
+                    let n = match read_stdin(&mut buf) {
+                        Ok(0) => break,
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    if let Some((_, sender)) = &*STDIN_SENDER.lock().unwrap() {
+                        sender.send(buf[..n].to_vec()).ok();
+                    }
+                }
+                // end of input, wake up the current reader
+                STDIN_SENDER.lock().unwrap().take();
+            });
+        });
+
+        Self {
+            id,
+            receiver,
+            buffer: vec![],
+        }
+    }
+}
+
+impl Drop for StdinReader {
+    fn drop(&mut self) {
+        close_stdin_reader(self.id);
+    }
+}
+
+// Stop sending stdin to the reader, unless a newer one took its place. The
+// reader gets the end of input once it has read what was already sent.
+fn close_stdin_reader(id: usize) {
+    let mut sender = STDIN_SENDER.lock().unwrap();
+    if let Some((current, _)) = &*sender {
+        if *current == id {
+            sender.take();
+        }
+    }
+}
+
+// Whether stdin should be read: a reader wants the bytes and no other
+// process was given the terminal.
+fn stdin_wanted() -> bool {
+    !STDIN_PAUSED.load(Ordering::SeqCst) && STDIN_SENDER.lock().unwrap().is_some()
+}
+
+// Wait for stdin to be readable, so the thread never blocks in a read it
+// can't be pulled out of when input is paused.
+fn stdin_ready(timeout: Duration) -> bool {
+    let mut fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
+
+// Read straight from the file descriptor, bytes left in the buffer of
+// `io::Stdin` wouldn't wake up poll.
+fn read_stdin(buf: &mut [u8]) -> io::Result<usize> {
+    let n = unsafe {
+        libc::read(
+            libc::STDIN_FILENO,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+impl Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer.is_empty() {
+            match self.receiver.recv() {
+                Ok(bytes) => self.buffer = bytes,
+                // replaced by a newer reader or stdin was closed
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.buffer.len());
+        buf[..n].copy_from_slice(&self.buffer[..n]);
+        self.buffer.drain(..n);
+        Ok(n)
+    }
+}
+
+/// StreamBackend renders to any writer and reads input from any reader, with
+/// a fixed terminal size. Raw mode is up to whatever is on the other end of
+/// the streams, so enabling or disabling it does nothing. The writer isn't
+/// taken for a terminal, set the color profile with `color::set_profile` when
+/// it is one.
+pub struct StreamBackend<W, R> {
+    writer: W,
+    reader: Option<R>,
+    size: (u16, u16),
+}
+
+impl<W: Write, R: Read + Send + 'static> StreamBackend<W, R> {
+    pub fn new(writer: W, reader: R, width: u16, height: u16) -> Self {
+        Self {
+            writer,
+            reader: Some(reader),
+            size: (width, height),
+        }
+    }
+}
+
+impl StreamBackend<SharedBuffer, io::Empty> {
+    /// in_memory creates a backend without input which renders to a buffer
+    /// that can be inspected while the program runs or after it exits.
+    pub fn in_memory(width: u16, height: u16) -> (Self, SharedBuffer) {
+        let buffer = SharedBuffer::default();
+        let backend = Self::new(buffer.clone(), io::empty(), width, height);
+        (backend, buffer)
+    }
+}
+
+impl<W: Write, R> Write for StreamBackend<W, R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Send, R: Read + Send + 'static> Backend for StreamBackend<W, R> {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.size)
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reader(&mut self) -> io::Result<Box<dyn Read + Send>> {
+        // the reader can only be handed out once, later callers get no input
+        match self.reader.take() {
+            Some(reader) => Ok(Box::new(reader)),
+            None => Ok(Box::new(io::empty())),
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// SharedBackend is a backend used by both the renderer and the panic hook.
+#[derive(Clone)]
+pub(crate) struct SharedBackend {
+    backend: Arc<Mutex<Box<dyn Backend>>>,
+}
+
+impl SharedBackend {
+    pub(crate) fn new(backend: Box<dyn Backend>) -> Self {
+        Self {
+            backend: Arc::new(Mutex::new(backend)),
+        }
+    }
+
+    // a panic while writing leaves the lock poisoned, the terminal must still
+    // be restored afterwards
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Backend>> {
+        self.backend
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Lock the backend unless it's already in use, e.g. by the thread which
+    /// is panicking.
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, Box<dyn Backend>>> {
+        match self.backend.try_lock() {
+            Ok(backend) => Some(backend),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    pub(crate) fn size(&self) -> io::Result<(u16, u16)> {
+        self.lock().size()
+    }
+
+    pub(crate) fn enable_raw_mode(&self) -> io::Result<()> {
+        self.lock().enable_raw_mode()
+    }
+
+    pub(crate) fn disable_raw_mode(&self) -> io::Result<()> {
+        self.lock().disable_raw_mode()
+    }
+
+    pub(crate) fn pause_input(&self) {
+        self.lock().pause_input()
+    }
+
+    pub(crate) fn resume_input(&self) {
+        self.lock().resume_input()
+    }
+}
+
+impl Write for SharedBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// SharedBuffer is a cloneable in-memory writer, every clone sees the bytes
+/// written through the others.
+#[derive(Clone, Default)]
+pub struct SharedBuffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn bytes(&self) -> Vec<u8> {
+        self.bytes.lock().unwrap().clone()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
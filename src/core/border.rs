@@ -0,0 +1,74 @@
+/// Border is the set of characters drawing the edges and the corners of a
+/// box, see `Style::with_border`. The edges are repeated along the sides,
+/// custom borders are made by setting the fields:
+///
+/// ```
+/// use moonlight::border::Border;
+///
+/// let dashed = Border {
+///     top: String::from("╌"),
+///     bottom: String::from("╌"),
+///     left: String::from("╎"),
+///     right: String::from("╎"),
+///     ..Border::normal()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Border {
+    pub top: String,
+    pub bottom: String,
+    pub left: String,
+    pub right: String,
+    pub top_left: String,
+    pub top_right: String,
+    pub bottom_left: String,
+    pub bottom_right: String,
+}
+
+impl Border {
+    fn from_chars(chars: [&str; 8]) -> Self {
+        let [top, bottom, left, right, top_left, top_right, bottom_left, bottom_right] = chars;
+        Self {
+            top: top.to_string(),
+            bottom: bottom.to_string(),
+            left: left.to_string(),
+            right: right.to_string(),
+            top_left: top_left.to_string(),
+            top_right: top_right.to_string(),
+            bottom_left: bottom_left.to_string(),
+            bottom_right: bottom_right.to_string(),
+        }
+    }
+
+    /// normal is drawn with thin lines and square corners.
+    pub fn normal() -> Self {
+        Self::from_chars(["─", "─", "│", "│", "┌", "┐", "└", "┘"])
+    }
+
+    /// rounded is drawn with thin lines and round corners.
+    pub fn rounded() -> Self {
+        Self::from_chars(["─", "─", "│", "│", "╭", "╮", "╰", "╯"])
+    }
+
+    /// thick is drawn with heavy lines.
+    pub fn thick() -> Self {
+        Self::from_chars(["━", "━", "┃", "┃", "┏", "┓", "┗", "┛"])
+    }
+
+    /// double is drawn with double lines.
+    pub fn double() -> Self {
+        Self::from_chars(["═", "═", "║", "║", "╔", "╗", "╚", "╝"])
+    }
+
+    /// hidden takes the room of a border without drawing it, to line up
+    /// boxes with and without borders.
+    pub fn hidden() -> Self {
+        Self::from_chars([" "; 8])
+    }
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
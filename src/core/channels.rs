@@ -4,29 +4,35 @@ use super::render_channel::RenderChannel;
 
 pub struct ChannelSender<MSG> {
     pub tx: Sender<MSG>,
+    render: RenderChannel,
 }
 
 impl<MSG> ChannelSender<MSG> {
-    pub fn send(&mut self, msg: MSG) {
-        self.tx.send(msg).ok();
-        RenderChannel::new().next_frame();
+    /// Send a message to the runtime. Returns false once the runtime is
+    /// gone, the message is dropped then.
+    pub fn send(&mut self, msg: MSG) -> bool {
+        let sent = self.tx.send(msg).is_ok();
+        self.render.next_frame();
+        sent
     }
 }
 
 pub struct Channel<MSG> {
     pub tx: Sender<MSG>,
     pub rx: Receiver<MSG>,
+    render: RenderChannel,
 }
 
 impl<MSG> Channel<MSG> {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(render: RenderChannel) -> Self {
         let (tx, rx) = channel();
-        Channel { tx, rx }
+        Channel { tx, rx, render }
     }
 
     pub fn sender(&mut self) -> ChannelSender<MSG> {
         ChannelSender {
             tx: self.tx.clone(),
+            render: self.render.clone(),
         }
     }
 }
@@ -1,8 +1,278 @@
+use std::{
+    env, fmt, io,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
 use csscolorparser::{Color, ParseError};
 use termion::color;
 
-pub fn convert_hex_rgb(color: &str) -> Result<color::Rgb, ParseError> {
+/// convert_hex_rgb parses a CSS color, e.g. "#FF5F87". The color is written
+/// with the best escape code the color profile supports, see `profile`.
+pub fn convert_hex_rgb(color: &str) -> Result<Rgb, ParseError> {
     let c = color.parse::<Color>()?;
     let (r, g, b, _) = c.rgba_u8();
-    Ok(color::Rgb(r, g, b))
+    Ok(Rgb(r, g, b))
+}
+
+/// Profile is the set of colors a terminal can show, from none to 24-bit
+/// colors. Profiles are ordered, a profile supports the colors of the lower
+/// ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Profile {
+    NoColor,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl Profile {
+    /// detect finds the profile of the terminal on stdout, where the default
+    /// backend renders, see `detect_for`.
+    pub fn detect() -> Self {
+        Self::detect_for(termion::is_tty(&io::stdout()))
+    }
+
+    /// detect_for finds the profile of the output of a backend: no color when
+    /// it isn't a terminal or NO_COLOR is set, otherwise it's guessed from
+    /// COLORTERM and TERM.
+    pub fn detect_for(is_terminal: bool) -> Self {
+        if !is_terminal {
+            return Self::NoColor;
+        }
+        Self::from_env(|key| env::var(key).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        // see https://no-color.org
+        if !var("NO_COLOR").unwrap_or_default().is_empty() {
+            return Self::NoColor;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        if term.is_empty() || term == "dumb" {
+            return Self::NoColor;
+        }
+
+        let colorterm = var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Self::TrueColor;
+        }
+
+        let truecolor_terms = [
+            "truecolor",
+            "24bit",
+            "direct",
+            "kitty",
+            "alacritty",
+            "wezterm",
+        ];
+        if truecolor_terms.iter().any(|t| term.contains(t)) {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+const UNKNOWN: u8 = u8::MAX;
+
+static PROFILE: AtomicU8 = AtomicU8::new(UNKNOWN);
+static PROFILE_SET: AtomicBool = AtomicBool::new(false);
+
+/// profile returns the color profile colors are downsampled to. A running
+/// program detects it from its backend, otherwise it's detected from stdout
+/// the first time it's needed, unless it was set with set_profile.
+pub fn profile() -> Profile {
+    match PROFILE.load(Ordering::Relaxed) {
+        UNKNOWN => {
+            let profile = Profile::detect();
+            store_profile(profile);
+            profile
+        }
+        n => PROFILES[n as usize],
+    }
+}
+
+/// set_profile overrides the detected color profile, e.g. to force colors
+/// when the output is piped.
+pub fn set_profile(profile: Profile) {
+    PROFILE_SET.store(true, Ordering::Relaxed);
+    store_profile(profile);
+}
+
+// Detect the profile of the backend a program renders to, unless it was set.
+pub(crate) fn detect_profile_for(is_terminal: bool) {
+    if !PROFILE_SET.load(Ordering::Relaxed) {
+        store_profile(Profile::detect_for(is_terminal));
+    }
+}
+
+fn store_profile(profile: Profile) {
+    let n = PROFILES.iter().position(|p| *p == profile).unwrap_or(0);
+    PROFILE.store(n as u8, Ordering::Relaxed);
+}
+
+const PROFILES: [Profile; 4] = [
+    Profile::NoColor,
+    Profile::Ansi16,
+    Profile::Ansi256,
+    Profile::TrueColor,
+];
+
+/// Rgb is a 24-bit color, written as the nearest color of the profile when
+/// used with `termion::color::Fg` or `Bg`. Nothing is written without colors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // The SGR parameters selecting the color, for the foreground or the
+    // background.
+    pub(crate) fn sgr(self, profile: Profile, background: bool) -> Option<String> {
+        let Rgb(r, g, b) = self;
+        let base = if background { 40 } else { 30 };
+        match profile {
+            Profile::NoColor => None,
+            Profile::TrueColor => Some(format!("{};2;{};{};{}", base + 8, r, g, b)),
+            Profile::Ansi256 => Some(format!("{};5;{}", base + 8, ansi256(self))),
+            Profile::Ansi16 => match ansi16(self) {
+                n @ 0..=7 => Some((base + n).to_string()),
+                n => Some((base + 60 + n - 8).to_string()),
+            },
+        }
+    }
+}
+
+impl color::Color for Rgb {
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.sgr(profile(), false) {
+            Some(sgr) => write!(f, "\x1B[{}m", sgr),
+            None => Ok(()),
+        }
+    }
+
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.sgr(profile(), true) {
+            Some(sgr) => write!(f, "\x1B[{}m", sgr),
+            None => Ok(()),
+        }
+    }
+}
+
+// The 16 ANSI colors, as xterm shows them.
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// Levels of the 6x6x6 color cube of the 256 colors palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn ansi16(c: Rgb) -> u8 {
+    let nearest = (0..ANSI16.len()).min_by_key(|&i| {
+        let (r, g, b) = ANSI16[i];
+        distance(c, Rgb(r, g, b))
+    });
+    nearest.unwrap_or(0) as u8
+}
+
+// The nearest of the color cube and the gray ramp, the first 16 colors are
+// left out as terminals change them.
+fn ansi256(c: Rgb) -> u8 {
+    let level = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| (i32::from(CUBE[i]) - i32::from(v)).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(c.0), level(c.1), level(c.2));
+    let cube = Rgb(CUBE[r], CUBE[g], CUBE[b]);
+
+    // grays go from 8 to 238 by steps of 10
+    let average = (u32::from(c.0) + u32::from(c.1) + u32::from(c.2)) / 3;
+    let step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let level = 8 + step * 10;
+    let gray = Rgb(level, level, level);
+
+    if distance(c, gray) < distance(c, cube) {
+        232 + step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+// Squared distance between colors, weighted for the way eyes see them.
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2) as u32;
+    2 * d(a.0, b.0) + 4 * d(a.1, b.1) + 3 * d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_of(vars: &[(&str, &str)]) -> Profile {
+        Profile::from_env(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn detects_profiles_from_the_environment() {
+        assert_eq!(profile_of(&[]), Profile::NoColor);
+        assert_eq!(profile_of(&[("TERM", "dumb")]), Profile::NoColor);
+        assert_eq!(
+            profile_of(&[("TERM", "xterm-256color"), ("NO_COLOR", "1")]),
+            Profile::NoColor
+        );
+        assert_eq!(
+            profile_of(&[("TERM", "xterm-256color"), ("NO_COLOR", "")]),
+            Profile::Ansi256
+        );
+        assert_eq!(
+            profile_of(&[("TERM", "xterm"), ("COLORTERM", "truecolor")]),
+            Profile::TrueColor
+        );
+        assert_eq!(profile_of(&[("TERM", "xterm-kitty")]), Profile::TrueColor);
+        assert_eq!(profile_of(&[("TERM", "xterm")]), Profile::Ansi16);
+        // nothing is guessed for output which isn't a terminal
+        assert_eq!(Profile::detect_for(false), Profile::NoColor);
+    }
+
+    #[test]
+    fn maps_colors_to_the_palettes() {
+        let red = Rgb(255, 0, 0);
+        assert_eq!(ansi256(red), 196);
+        assert_eq!(ansi16(red), 9);
+        assert_eq!(red.sgr(Profile::Ansi16, false).unwrap(), "91");
+        assert_eq!(red.sgr(Profile::Ansi256, true).unwrap(), "48;5;196");
+        assert_eq!(red.sgr(Profile::TrueColor, false).unwrap(), "38;2;255;0;0");
+        assert_eq!(red.sgr(Profile::NoColor, false), None);
+    }
+
+    #[test]
+    fn maps_grays_to_the_gray_ramp() {
+        assert_eq!(ansi256(Rgb(8, 8, 8)), 232);
+        assert_eq!(ansi256(Rgb(128, 128, 128)), 244);
+        assert_eq!(ansi256(Rgb(238, 238, 238)), 255);
+        // black and white are in the color cube
+        assert_eq!(ansi256(Rgb(0, 0, 0)), 16);
+        assert_eq!(ansi256(Rgb(255, 255, 255)), 231);
+    }
 }
@@ -1,24 +1,312 @@
-use std::{thread::sleep, time::Duration};
+use std::{
+    any::Any,
+    io,
+    process::{Command, ExitStatus},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::sleep,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::{BatchCmd, Cmd};
+/// Cmd is an IO operation which produces messages for the reducer. Commands
+/// are returned by the reducer and executed by the runtime on its worker
+/// threads, build them with `Cmd::new` or the functions of this module.
+///
+/// Any `FnOnce() -> Message` closure converts into a command, so reducers can
+/// keep returning plain closures.
+pub struct Cmd<Message> {
+    pub(crate) kind: Kind<Message>,
+}
+
+pub type BatchCmd<Message> = Vec<Cmd<Message>>;
+
+/// Value passed to `quit_with`.
+pub(crate) type ExitValue = Box<dyn Any + Send>;
+
+pub(crate) enum Kind<Message> {
+    /// Produces a single message.
+    Msg(Box<dyn FnOnce() -> Message + Send>),
+    /// A message known up front, delivered right away and in order.
+    Ready(Message),
+    /// Run concurrently.
+    Batch(BatchCmd<Message>),
+    /// Run one after the other.
+    Sequence(BatchCmd<Message>),
+    /// Emits any number of messages until the function returns.
+    Stream(Box<dyn FnOnce(Emitter<Message>) + Send>),
+    /// Messages are dropped once the handle is cancelled.
+    Cancellable(CancelHandle, Box<Cmd<Message>>),
+    /// Hand the terminal over, see `suspend` and `exec`.
+    Handoff(Handoff<Message>),
+    /// Stop the runtime, with an exit value.
+    Quit(Option<ExitValue>),
+}
+
+impl<Message: 'static> Cmd<Message> {
+    pub fn new(f: impl FnOnce() -> Message + Send + 'static) -> Self {
+        Self {
+            kind: Kind::Msg(Box::new(f)),
+        }
+    }
+
+    /// map converts the messages produced by this command, which is how a
+    /// component's commands are lifted into the program's message type.
+    pub fn map<O: 'static>(self, f: impl Fn(Message) -> O + Send + Sync + 'static) -> Cmd<O> {
+        self.map_with(Arc::new(f))
+    }
+
+    fn map_with<O: 'static>(self, f: Arc<dyn Fn(Message) -> O + Send + Sync>) -> Cmd<O> {
+        let kind = match self.kind {
+            Kind::Msg(cmd) => Kind::Msg(Box::new(move || f(cmd()))),
+            Kind::Ready(msg) => Kind::Ready(f(msg)),
+            Kind::Batch(cmds) => Kind::Batch(map_all(cmds, f)),
+            Kind::Sequence(cmds) => Kind::Sequence(map_all(cmds, f)),
+            Kind::Stream(stream) => Kind::Stream(Box::new(move |emitter: Emitter<O>| {
+                stream(Emitter::new(move |msg| emitter.emit(f(msg))))
+            })),
+            Kind::Cancellable(handle, cmd) => Kind::Cancellable(handle, Box::new(cmd.map_with(f))),
+            Kind::Handoff(Handoff::Suspend) => Kind::Handoff(Handoff::Suspend),
+            Kind::Handoff(Handoff::Exec(command, done)) => Kind::Handoff(Handoff::Exec(
+                command,
+                Box::new(move |status| f(done(status))),
+            )),
+            Kind::Quit(value) => Kind::Quit(value),
+        };
+        Cmd { kind }
+    }
+}
+
+/// Handoff is a command which needs the terminal for itself, the runtime
+/// releases it while the command runs.
+pub(crate) enum Handoff<Message> {
+    Suspend,
+    Exec(
+        Box<Command>,
+        Box<dyn FnOnce(io::Result<ExitStatus>) -> Message + Send>,
+    ),
+}
+
+impl<Message, F> From<F> for Cmd<Message>
+where
+    Message: 'static,
+    F: FnOnce() -> Message + Send + 'static,
+{
+    fn from(f: F) -> Self {
+        Cmd::new(f)
+    }
+}
 
-pub fn tick<MSG: 'static>(d: Duration, fun: impl Fn() -> MSG + Send + Sync + 'static) -> Cmd<MSG> {
-    Box::new(move || {
+fn map_all<I: 'static, O: 'static>(
+    cmds: BatchCmd<I>,
+    f: Arc<dyn Fn(I) -> O + Send + Sync>,
+) -> BatchCmd<O> {
+    cmds.into_iter()
+        .map(|cmd| cmd.map_with(f.clone()))
+        .collect()
+}
+
+/// Emitter sends the messages of a streaming command to the runtime, see
+/// `stream`. It can be cloned and moved to other threads.
+pub struct Emitter<Message> {
+    send: Arc<dyn Fn(Message) -> bool + Send + Sync>,
+}
+
+impl<Message> Clone for Emitter<Message> {
+    fn clone(&self) -> Self {
+        Self {
+            send: self.send.clone(),
+        }
+    }
+}
+
+impl<Message> Emitter<Message> {
+    pub(crate) fn new(send: impl Fn(Message) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            send: Arc::new(send),
+        }
+    }
+
+    /// Send a message to the reducer. Returns false when the command was
+    /// cancelled or the program stopped, in which case the message was
+    /// dropped and the stream should stop.
+    pub fn emit(&self, msg: Message) -> bool {
+        (self.send)(msg)
+    }
+}
+
+/// CancelHandle aborts the command it was created with, see `cancellable`.
+/// It's cheap to clone, so it can be kept in the model.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// message sends a message the reducer already knows, e.g. to hand it to
+/// the renderer. Unlike `Cmd::new`, whose closures run concurrently on the
+/// workers, messages sent this way arrive in the order their commands were
+/// returned.
+pub fn message<MSG>(msg: MSG) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Ready(msg),
+    }
+}
+
+/// none is a command which does nothing.
+pub fn none<MSG>() -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Batch(vec![]),
+    }
+}
+
+/// batch runs the commands concurrently, messages are delivered in whatever
+/// order the commands finish.
+pub fn batch<MSG>(cmds: BatchCmd<MSG>) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Batch(cmds),
+    }
+}
+
+/// sequence runs the commands one at a time, each one starts after the
+/// message of the previous one was sent to the runtime.
+pub fn sequence<MSG>(cmds: BatchCmd<MSG>) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Sequence(cmds),
+    }
+}
+
+/// quit stops the runtime once the messages it already received are reduced
+/// and the last frame is rendered.
+pub fn quit<MSG>() -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Quit(None),
+    }
+}
+
+/// quit_with is like quit, but the value is handed back to the caller of
+/// `Runtime::run`, see `Exit::value`. A prompt uses it to return the answer.
+pub fn quit_with<MSG, T: Any + Send>(value: T) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Quit(Some(Box::new(value))),
+    }
+}
+
+/// suspend stops the program like Ctrl-Z does in a shell: the terminal is
+/// restored and the process gets SIGTSTP. Once resumed with SIGCONT (e.g. by
+/// `fg`) the terminal is set up again and the view repainted.
+pub fn suspend<MSG>() -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Handoff(Handoff::Suspend),
+    }
+}
+
+/// exec runs an external program, e.g. `$EDITOR`, with the terminal released
+/// and waits for it to exit. The function receives its exit status and
+/// returns the message to send. The program is not rendered in the meantime.
+pub fn exec<MSG>(
+    command: Command,
+    fun: impl FnOnce(io::Result<ExitStatus>) -> MSG + Send + 'static,
+) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Handoff(Handoff::Exec(Box::new(command), Box::new(fun))),
+    }
+}
+
+/// cancellable wraps a command so it can be aborted with the returned handle,
+/// e.g. when the user navigates away from a screen which is still loading.
+/// A command which already started keeps running, but its messages are
+/// dropped instead of reaching the reducer.
+pub fn cancellable<MSG>(cmd: Cmd<MSG>) -> (Cmd<MSG>, CancelHandle) {
+    let handle = CancelHandle::default();
+    let cmd = Cmd {
+        kind: Kind::Cancellable(handle.clone(), Box::new(cmd)),
+    };
+    (cmd, handle)
+}
+
+/// stream runs a command which emits zero, one or many messages over time,
+/// e.g. the lines printed by a child process or the progress of a download.
+/// The stream is done when the function returns; put it in a `sequence` to
+/// send a message once it completes.
+///
+/// Streams get a thread of their own, so a long running one doesn't take a
+/// worker away from the other commands. So does a sequence with a stream in
+/// it, since it waits for the stream to finish.
+pub fn stream<MSG>(fun: impl FnOnce(Emitter<MSG>) + Send + 'static) -> Cmd<MSG> {
+    Cmd {
+        kind: Kind::Stream(Box::new(fun)),
+    }
+}
+
+/// from_iter emits every item of the iterator, in order, until it's exhausted
+/// or the command is cancelled. A channel's receiver can be streamed with
+/// `from_iter(receiver)`.
+pub fn from_iter<MSG, I>(iter: I) -> Cmd<MSG>
+where
+    I: IntoIterator<Item = MSG> + Send + 'static,
+{
+    stream(move |emitter| {
+        for msg in iter {
+            if !emitter.emit(msg) {
+                break;
+            }
+        }
+    })
+}
+
+/// tick produces a message after the given duration.
+pub fn tick<MSG: 'static>(d: Duration, fun: impl FnOnce() -> MSG + Send + 'static) -> Cmd<MSG> {
+    Cmd::new(move || {
         sleep(d);
         fun()
     })
 }
 
-pub fn map_batch<I: 'static, O>(cmds: BatchCmd<I>) -> Vec<impl Fn() -> O + Send + 'static>
+/// every is like tick, but aligned with the system clock: an `every` of one
+/// second fires at the start of the next second, not one second from now.
+/// The function receives the time the tick fired.
+pub fn every<MSG: 'static>(
+    d: Duration,
+    fun: impl FnOnce(SystemTime) -> MSG + Send + 'static,
+) -> Cmd<MSG> {
+    Cmd::new(move || {
+        sleep_until_next(d);
+        fun(SystemTime::now())
+    })
+}
+
+/// sleep_until_next sleeps until the system clock reaches the next multiple
+/// of the duration.
+pub(crate) fn sleep_until_next(d: Duration) {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let d_nanos = d.as_nanos().max(1);
+    let elapsed = since_epoch.as_nanos() % d_nanos;
+    sleep(Duration::from_nanos((d_nanos - elapsed) as u64));
+}
+
+pub fn map_batch<I: 'static, O>(cmds: BatchCmd<I>) -> BatchCmd<O>
 where
-    O: From<I>,
+    O: From<I> + 'static,
 {
-    cmds.into_iter().map(|f| move || O::from(f())).collect()
+    cmds.into_iter().map(map).collect()
 }
 
-pub fn map<I: 'static + Sync, O>(cmd: Cmd<I>) -> impl Fn() -> O + Send + 'static
+pub fn map<I: 'static, O>(cmd: Cmd<I>) -> Cmd<O>
 where
-    O: From<I>,
+    O: From<I> + 'static,
 {
-    move || O::from(cmd())
+    cmd.map(O::from)
 }
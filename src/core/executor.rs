@@ -0,0 +1,295 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::commands::{CancelHandle, Cmd, Emitter, ExitValue, Handoff, Kind};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Executor runs commands on a fixed number of worker threads, so a command
+/// which sleeps or blocks never holds up input handling or rendering. When
+/// every worker is busy new jobs wait in a queue.
+#[derive(Clone)]
+pub(crate) struct Executor {
+    sender: Sender<Job>,
+}
+
+impl Executor {
+    pub(crate) fn new(workers: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            thread::spawn(move || work(receiver));
+        }
+
+        Self { sender }
+    }
+
+    pub(crate) fn spawn<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.sender.send(Box::new(job)).ok();
+    }
+}
+
+// Whether a stream runs somewhere in the command.
+fn has_stream<Message>(cmd: &Cmd<Message>) -> bool {
+    match &cmd.kind {
+        Kind::Stream(_) => true,
+        Kind::Batch(cmds) | Kind::Sequence(cmds) => cmds.iter().any(has_stream),
+        Kind::Cancellable(_, cmd) => has_stream(cmd),
+        _ => false,
+    }
+}
+
+// Workers stop once the executor is dropped and the queue is drained. They
+// are never joined: one of them may be sleeping in a long tick. A command
+// which panics loses its message, but its worker goes on with the next job.
+fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = receiver.lock().unwrap().recv();
+        match job {
+            Ok(job) => {
+                panic::catch_unwind(AssertUnwindSafe(job)).ok();
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// Context is where commands send their messages, what quitting does and who
+/// takes care of the commands needing the terminal.
+/// Without an executor every command runs on the calling thread, in order,
+/// which is what the harness uses to stay deterministic.
+pub(crate) struct Context<Message> {
+    emit: Arc<dyn Fn(Message) -> bool + Send + Sync>, // false once stopped
+    quit: Arc<dyn Fn(Option<ExitValue>) + Send + Sync>,
+    handoff: Arc<dyn Fn(Handoff<Message>) + Send + Sync>,
+    executor: Option<Executor>,
+    cancel: Vec<CancelHandle>,
+}
+
+impl<Message> Clone for Context<Message> {
+    fn clone(&self) -> Self {
+        Self {
+            emit: self.emit.clone(),
+            quit: self.quit.clone(),
+            handoff: self.handoff.clone(),
+            executor: self.executor.clone(),
+            cancel: self.cancel.clone(),
+        }
+    }
+}
+
+impl<Message: Send + 'static> Context<Message> {
+    pub(crate) fn new(
+        emit: impl Fn(Message) -> bool + Send + Sync + 'static,
+        quit: impl Fn(Option<ExitValue>) + Send + Sync + 'static,
+        handoff: impl Fn(Handoff<Message>) + Send + Sync + 'static,
+        executor: Option<Executor>,
+    ) -> Self {
+        Self {
+            emit: Arc::new(emit),
+            quit: Arc::new(quit),
+            handoff: Arc::new(handoff),
+            executor,
+            cancel: vec![],
+        }
+    }
+
+    /// Start executing a command, returns as soon as it's handed to the
+    /// workers.
+    pub(crate) fn execute(&self, cmd: Cmd<Message>) {
+        let executor = match &self.executor {
+            Some(executor) => executor,
+            None => return self.run(cmd),
+        };
+
+        match cmd.kind {
+            Kind::Msg(f) => {
+                let ctx = self.clone();
+                executor.spawn(move || ctx.deliver(f()));
+            }
+            // nothing to wait for, delivering it here keeps the order
+            Kind::Ready(msg) => self.deliver(msg),
+            Kind::Batch(cmds) => {
+                for cmd in cmds {
+                    self.execute(cmd);
+                }
+            }
+            Kind::Sequence(cmds) => {
+                // a stream may never end and the sequence would wait for it,
+                // keep it off the worker pool like the stream itself
+                let own_thread = cmds.iter().any(has_stream);
+                let ctx = self.clone();
+                let run = move || {
+                    for cmd in cmds {
+                        ctx.run(cmd);
+                    }
+                };
+                if own_thread {
+                    thread::spawn(run);
+                } else {
+                    executor.spawn(run);
+                }
+            }
+            Kind::Stream(stream) => {
+                // streams may never end, keep them off the worker pool
+                let emitter = self.emitter();
+                thread::spawn(move || stream(emitter));
+            }
+            Kind::Cancellable(handle, cmd) => {
+                if !handle.is_cancelled() {
+                    self.with_cancel(handle).execute(*cmd);
+                }
+            }
+            Kind::Handoff(handoff) => self.handoff(handoff),
+            Kind::Quit(value) => self.quit(value),
+        }
+    }
+
+    /// Execute a command on the current thread and wait for it to finish.
+    fn run(&self, cmd: Cmd<Message>) {
+        match cmd.kind {
+            Kind::Msg(f) => self.deliver(f()),
+            Kind::Ready(msg) => self.deliver(msg),
+            // a batch inside a sequence must finish before the sequence goes
+            // on, don't wait for the workers since they may all be busy
+            Kind::Batch(cmds) if self.executor.is_some() => {
+                let handles: Vec<_> = cmds
+                    .into_iter()
+                    .map(|cmd| {
+                        let ctx = self.clone();
+                        thread::spawn(move || ctx.run(cmd))
+                    })
+                    .collect();
+                for handle in handles {
+                    handle.join().ok();
+                }
+            }
+            Kind::Batch(cmds) | Kind::Sequence(cmds) => {
+                for cmd in cmds {
+                    self.run(cmd);
+                }
+            }
+            // sequences with a stream are never run by the workers
+            Kind::Stream(stream) => stream(self.emitter()),
+            Kind::Cancellable(handle, cmd) => {
+                if !handle.is_cancelled() {
+                    self.with_cancel(handle).run(*cmd);
+                }
+            }
+            Kind::Handoff(handoff) => self.handoff(handoff),
+            Kind::Quit(value) => self.quit(value),
+        }
+    }
+
+    fn deliver(&self, msg: Message) {
+        if self.cancelled() {
+            return;
+        }
+        (self.emit)(msg);
+    }
+
+    fn emitter(&self) -> Emitter<Message> {
+        let emit = self.emit.clone();
+        let cancel = self.cancel.clone();
+        Emitter::new(move |msg| {
+            if cancel.iter().any(CancelHandle::is_cancelled) {
+                return false;
+            }
+            emit(msg)
+        })
+    }
+
+    fn quit(&self, value: Option<ExitValue>) {
+        if !self.cancelled() {
+            (self.quit)(value);
+        }
+    }
+
+    fn handoff(&self, handoff: Handoff<Message>) {
+        if !self.cancelled() {
+            (self.handoff)(handoff);
+        }
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.iter().any(CancelHandle::is_cancelled)
+    }
+
+    fn with_cancel(&self, handle: CancelHandle) -> Self {
+        let mut ctx = self.clone();
+        ctx.cancel.push(handle);
+        ctx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::commands;
+
+    #[test]
+    fn workers_survive_panicking_jobs() {
+        let executor = Executor::new(1);
+        let (sender, receiver) = channel();
+        executor.spawn(|| panic!("command failed"));
+        executor.spawn(move || sender.send(()).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn endless_streams_stop_once_messages_are_refused() {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        let ctx = Context::new(
+            move |msg| sender.lock().unwrap().send(msg).is_ok(),
+            |_| {},
+            |_| {},
+            Some(Executor::new(1)),
+        );
+        let (stopped, stopped_receiver) = channel();
+        let endless = commands::stream(move |emitter| {
+            while emitter.emit(0) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            stopped.send(()).unwrap();
+        });
+
+        // the stream doesn't hold the only worker, even in a sequence
+        ctx.execute(commands::sequence(vec![endless]));
+        ctx.execute(Cmd::new(|| 1));
+        let timeout = Duration::from_secs(5);
+        while receiver.recv_timeout(timeout).unwrap() != 1 {}
+
+        drop(receiver);
+        assert!(stopped_receiver.recv_timeout(timeout).is_ok());
+    }
+
+    #[test]
+    fn messages_arrive_in_order() {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        let ctx = Context::new(
+            move |msg| sender.lock().unwrap().send(msg).is_ok(),
+            |_| {},
+            |_| {},
+            Some(Executor::new(4)),
+        );
+
+        for i in 0..50 {
+            ctx.execute(commands::message(i));
+        }
+        ctx.execute(commands::batch((50..100).map(commands::message).collect()));
+        let received: Vec<i32> = receiver.try_iter().collect();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+}
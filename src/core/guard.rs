@@ -0,0 +1,105 @@
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, Once,
+    },
+    thread::{self, ThreadId},
+};
+
+use addy::Signal::{self, SIGINT, SIGTERM};
+
+type Restore = Box<dyn Fn() + Send>;
+
+// runtimes which are running, by id, with the thread running them
+static GUARDS: Mutex<Vec<(usize, ThreadId, Restore)>> = Mutex::new(Vec::new());
+static HOOK: Once = Once::new();
+
+/// PanicGuard restores the terminal of a runtime when the thread running it
+/// panics, e.g. in the reducer or the view, before the panic message is
+/// printed. The panic hook is installed once and chained to the previous one,
+/// dropping the guard disarms it.
+pub(crate) struct PanicGuard {
+    id: usize,
+}
+
+impl PanicGuard {
+    pub(crate) fn new(id: usize, restore: impl Fn() + Send + 'static) -> Self {
+        HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore_current_thread();
+                previous(info);
+            }));
+        });
+
+        let thread = thread::current().id();
+        lock_guards().push((id, thread, Box::new(restore)));
+        Self { id }
+    }
+}
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        lock_guards().retain(|(id, _, _)| *id != self.id);
+    }
+}
+
+fn restore_current_thread() {
+    let current = thread::current().id();
+    // the guards may be locked by the panicking thread itself
+    if let Ok(mut guards) = GUARDS.try_lock() {
+        guards.retain(|(_, thread, restore)| {
+            if *thread != current {
+                return true;
+            }
+            restore();
+            false
+        });
+    }
+}
+
+fn lock_guards() -> std::sync::MutexGuard<'static, Vec<(usize, ThreadId, Restore)>> {
+    GUARDS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+const STOP_SIGNALS: [Signal; 2] = [SIGINT, SIGTERM];
+
+// runtimes listening to the stop signals
+static LISTENING: AtomicUsize = AtomicUsize::new(0);
+
+/// SignalGuard calls back when the process is asked to stop with SIGINT or
+/// SIGTERM, so the runtime can restore the terminal on its way out instead of
+/// being killed. Once the last guard is dropped the signals get their default
+/// behavior back.
+pub(crate) struct SignalGuard {
+    name: String,
+}
+
+impl SignalGuard {
+    pub(crate) fn new(name: String, on_signal: impl Fn(Signal) + Clone + Send + 'static) -> Self {
+        LISTENING.fetch_add(1, Ordering::SeqCst);
+        for signal in STOP_SIGNALS.iter() {
+            addy::mediate(*signal)
+                .register(&name, on_signal.clone())
+                .and_then(|handle| handle.enable())
+                .expect("could not register the stop signal handlers");
+        }
+        Self { name }
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        let last = LISTENING.fetch_sub(1, Ordering::SeqCst) == 1;
+        for signal in STOP_SIGNALS.iter() {
+            let mut handle = addy::mediate(*signal);
+            handle.remove(&self.name).ok();
+            if last {
+                handle.release().ok();
+            }
+        }
+    }
+}
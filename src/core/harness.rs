@@ -0,0 +1,333 @@
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::{
+    commands::{ExitValue, Handoff},
+    executor::Context,
+};
+use crate::{
+    input::InputEvent,
+    store::{Middleware, Store},
+    subscriptions::Subscriptions,
+    BatchCmd, Cmd, Initialize,
+};
+
+/// Window size delivered when a harness starts, the runtime does the same with
+/// the real terminal size.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Messages a command may emit during a step, see `Harness::with_stream_limit`.
+const DEFAULT_STREAM_LIMIT: usize = 100;
+
+/// Harness drives a program without a terminal. It takes the same reducer,
+/// initialize, input and view functions as `Runtime`, but events are injected
+/// by hand and every rendered view is kept as a frame, so whole programs can
+/// be snapshot tested in CI.
+///
+/// Commands never run in the background: they are queued and only executed,
+/// on the calling thread, when `step` is called, one round at a time. Streams
+/// run there too, until they return or emit more messages than the stream
+/// limit allows.
+pub struct Harness<Model: Sync, Message, Input, View, Reducer> {
+    store: Store<Model, Message, Reducer>,
+    pending: BatchCmd<Message>,
+    commands: Context<Message>,
+    outbox: Arc<Mutex<Vec<Message>>>, // messages produced by commands
+    exit: Arc<Mutex<Option<Option<ExitValue>>>>, // set once quit
+    stream_limit: Arc<AtomicUsize>,
+    subscriptions: Option<Subscriptions<Model, Message>>,
+    input: Input,
+    view: View,
+    frames: Vec<String>,
+}
+
+impl<Model, Message, Input, View, Reducer, Command> Harness<Model, Message, Input, View, Reducer>
+where
+    Model: Clone + Send + Sync + 'static,
+    Message: 'static + Send,
+    Input: Fn(InputEvent) -> Option<Message>,
+    View: Fn(&Model) -> String,
+    Reducer: Fn(Model, Message) -> (Model, Vec<Command>),
+    Command: Into<Cmd<Message>>,
+{
+    pub fn new(
+        reducer: Reducer,
+        initial: Initialize<Model, Message>,
+        input: Input,
+        view: View,
+    ) -> Self {
+        let (model, cmd) = initial();
+
+        let outbox = Arc::new(Mutex::new(vec![]));
+        let exit = Arc::new(Mutex::new(None));
+        let stream_limit = Arc::new(AtomicUsize::new(DEFAULT_STREAM_LIMIT));
+        let commands = {
+            let emit_outbox = outbox.clone();
+            let exec_outbox = outbox.clone();
+            let exit = exit.clone();
+            let limit = stream_limit.clone();
+            Context::new(
+                // the outbox is emptied after every command, past the limit
+                // messages are refused so that endless streams stop
+                move |msg| {
+                    let mut outbox = emit_outbox.lock().unwrap();
+                    if outbox.len() >= limit.load(Ordering::Relaxed) {
+                        return false;
+                    }
+                    outbox.push(msg);
+                    true
+                },
+                move |value: Option<ExitValue>| {
+                    let mut exit = exit.lock().unwrap();
+                    if exit.is_none() || value.is_some() {
+                        *exit = Some(value);
+                    }
+                },
+                // there is no terminal to suspend, external programs still run
+                move |handoff| {
+                    if let Handoff::Exec(mut command, done) = handoff {
+                        let msg = done(command.status());
+                        exec_outbox.lock().unwrap().push(msg);
+                    }
+                },
+                None,
+            )
+        };
+
+        let mut harness = Self {
+            store: Store::new(reducer, model),
+            pending: vec![],
+            commands,
+            outbox,
+            exit,
+            stream_limit,
+            subscriptions: None,
+            input,
+            view,
+            frames: vec![],
+        };
+
+        // queue fist command
+        if let Some(cmd) = cmd {
+            harness.pending.push(cmd);
+        }
+
+        harness.render();
+        harness.resize(DEFAULT_SIZE.0, DEFAULT_SIZE.1);
+        harness
+    }
+
+    /// Will execute before reducer
+    pub fn with_middleware(mut self, middleware: Middleware<Model, Message, Reducer>) -> Self {
+        self.store.middleware(middleware);
+        self
+    }
+
+    /// Limit how many messages a command may emit during a step, 100 by
+    /// default. Past the limit `Emitter::emit` returns false, which stops
+    /// streams that never end on their own (e.g. a clock). Streams ignoring
+    /// the result of emit still run forever, as does any command that never
+    /// returns.
+    pub fn with_stream_limit(self, limit: usize) -> Self {
+        self.stream_limit.store(limit, Ordering::Relaxed);
+        self
+    }
+
+    /// Subscriptions are never started by the harness, see `subscriptions`
+    /// to check which ones the program wants.
+    pub fn with_subscriptions(mut self, subscriptions: Subscriptions<Model, Message>) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+
+    /// Pass an event through the input function and dispatch the resulting
+    /// message, if any.
+    pub fn input(&mut self, event: InputEvent) -> &mut Self {
+        if let Some(msg) = (self.input)(event) {
+            self.send(msg);
+        }
+        self
+    }
+
+    /// Same as calling `input` for every event, in order.
+    pub fn inputs<I: IntoIterator<Item = InputEvent>>(&mut self, events: I) -> &mut Self {
+        for event in events {
+            self.input(event);
+        }
+        self
+    }
+
+    /// Simulate the terminal being resized.
+    pub fn resize(&mut self, width: u16, height: u16) -> &mut Self {
+        self.input(InputEvent::WindowSize { width, height })
+    }
+
+    /// Dispatch a message straight to the reducer and render a frame.
+    pub fn send(&mut self, msg: Message) -> &mut Self {
+        let cmds = self.store.dispatch(msg);
+        self.pending.extend(cmds);
+        self.render();
+        self
+    }
+
+    /// Execute the commands queued so far, in order, and dispatch their
+    /// messages. Commands returned while doing so are left for the next step,
+    /// so commands that reschedule themselves (e.g. a spinner tick) can't loop
+    /// forever. Returns how many commands were executed.
+    pub fn step(&mut self) -> usize {
+        let pending: BatchCmd<Message> = self.pending.drain(..).collect();
+        let executed = pending.len();
+        for cmd in pending {
+            self.commands.execute(cmd);
+            let messages: Vec<Message> = self.outbox.lock().unwrap().drain(..).collect();
+            for msg in messages {
+                self.send(msg);
+            }
+        }
+        executed
+    }
+
+    /// Number of commands waiting for the next step.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether a quit command was executed.
+    pub fn has_quit(&self) -> bool {
+        self.exit.lock().unwrap().is_some()
+    }
+
+    /// The value given to `commands::quit_with`, if one of type `T` was set.
+    pub fn exit_value<T: Any + Clone>(&self) -> Option<T> {
+        match &*self.exit.lock().unwrap() {
+            Some(Some(value)) => value.downcast_ref().cloned(),
+            _ => None,
+        }
+    }
+
+    /// Keys of the subscriptions the program wants for its current model.
+    pub fn subscriptions(&self) -> Vec<String> {
+        match self.subscriptions {
+            Some(subscriptions) => subscriptions(&self.store.model())
+                .iter()
+                .map(|sub| sub.key().to_string())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        self.store.model()
+    }
+
+    /// Every frame rendered so far, the first one being the initial view.
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+
+    pub fn last_frame(&self) -> &str {
+        self.frames.last().map(String::as_str).unwrap_or_default()
+    }
+
+    fn render(&mut self) {
+        let frame = (self.view)(&self.store.model());
+        self.frames.push(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands,
+        input::{Key, KeyEvent},
+    };
+
+    #[derive(Clone)]
+    struct Model(i32);
+
+    #[derive(Clone)]
+    enum Msg {
+        Increment,
+        Resized(u16),
+        Count,
+        Quit,
+    }
+
+    fn reducer(model: Model, msg: Msg) -> (Model, BatchCmd<Msg>) {
+        match msg {
+            Msg::Increment => (Model(model.0 + 1), vec![]),
+            Msg::Resized(width) => (Model(width as i32), vec![Cmd::new(|| Msg::Increment)]),
+            Msg::Count => {
+                let count = commands::stream(|emitter| while emitter.emit(Msg::Increment) {});
+                (model, vec![count])
+            }
+            Msg::Quit => {
+                let value = model.0;
+                (model, vec![commands::quit_with(value)])
+            }
+        }
+    }
+
+    fn initialize() -> (Model, Option<Cmd<Msg>>) {
+        (Model(0), None)
+    }
+
+    fn input(event: InputEvent) -> Option<Msg> {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: Key::Char('+'),
+                ..
+            }) => Some(Msg::Increment),
+            InputEvent::Key(KeyEvent {
+                key: Key::Char('q'),
+                ..
+            }) => Some(Msg::Quit),
+            InputEvent::WindowSize { width, .. } => Some(Msg::Resized(width)),
+            _ => None,
+        }
+    }
+
+    fn view(model: &Model) -> String {
+        model.0.to_string()
+    }
+
+    #[test]
+    fn renders_a_frame_per_message() {
+        let mut harness = Harness::new(reducer, initialize, input, view);
+        assert_eq!(harness.frames(), ["0", "80"]);
+
+        harness
+            .input(InputEvent::Key(Key::Char('+').into()))
+            .input(InputEvent::Key(Key::Char('x').into()));
+        assert_eq!(harness.last_frame(), "81");
+
+        // the command returned on resize only runs on step
+        assert_eq!(harness.pending(), 1);
+        assert_eq!(harness.step(), 1);
+        assert_eq!(harness.last_frame(), "82");
+        assert_eq!(harness.step(), 0);
+
+        harness.input(InputEvent::Key(Key::Char('q').into()));
+        assert!(!harness.has_quit());
+        harness.step();
+        assert!(harness.has_quit());
+        assert_eq!(harness.exit_value::<i32>(), Some(82));
+    }
+
+    #[test]
+    fn stops_endless_streams() {
+        let mut harness = Harness::new(reducer, initialize, input, view).with_stream_limit(5);
+        harness.step();
+        assert_eq!(harness.last_frame(), "81");
+
+        harness.send(Msg::Count);
+        assert_eq!(harness.step(), 1);
+        assert_eq!(harness.last_frame(), "86");
+    }
+}
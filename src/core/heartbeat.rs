@@ -1,21 +1,22 @@
-use std::{
-    mem,
-    sync::{Arc, Mutex, Once},
-};
+use std::sync::{Arc, Mutex};
 
 pub(crate) enum Health {
     Alive,
     Dead,
 }
 
+/// Heartbeat tells whether a runtime is still running, every runtime has its
+/// own. It's killed by the quit command.
 #[derive(Clone)]
-pub struct Heartbeat {
+pub(crate) struct Heartbeat {
     is_alive: Arc<Mutex<Health>>,
 }
 
 impl Heartbeat {
     pub(crate) fn new() -> Self {
-        singleton()
+        Self {
+            is_alive: Arc::new(Mutex::new(Health::Alive)),
+        }
     }
 
     pub(crate) fn is_dead(&self) -> bool {
@@ -26,30 +27,8 @@ impl Heartbeat {
         }
     }
 
-    fn kill(&self) {
+    pub(crate) fn kill(&self) {
         let mut alive = self.is_alive.lock().unwrap();
         *alive = Health::Dead;
     }
-
-    pub fn stop() {
-        let heartbeat = Heartbeat::new();
-        heartbeat.kill();
-    }
-}
-
-fn singleton() -> Heartbeat {
-    static mut HEARTBEAT: *const Heartbeat = 0 as *const Heartbeat;
-    static ONCE: Once = Once::new();
-
-    unsafe {
-        ONCE.call_once(|| {
-            let singleton = Heartbeat {
-                is_alive: Arc::new(Mutex::new(Health::Alive)),
-            };
-
-            HEARTBEAT = mem::transmute(Box::new(singleton));
-        });
-
-        (*HEARTBEAT).clone()
-    }
 }
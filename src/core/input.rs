@@ -1,18 +1,57 @@
-use termion::input::TermRead;
+use bitflags::bitflags;
 
-use std::io::stdin;
+use std::io::{self, Read};
 
-use super::channels::ChannelSender;
+use super::{channels::ChannelSender, parser::Parser};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum InputEvent {
-    Key(Key),                 // termion type
-    MouseButton(MouseButton), // termion type
+    Key(KeyEvent),
+    Mouse(MouseEvent), // only reported when the runtime is built with_mouse()
     WindowSize { width: u16, height: u16 },
+    Paste(String), // text pasted in the terminal, line breaks are '\n'
+    FocusGained,   // the terminal window gained focus
+    FocusLost,     // the terminal window lost focus
 }
 
 // Mapping types from termion and not exposes termion crate
 
+bitflags! {
+    /// Modifier keys held down while an event was produced.
+    pub struct Modifiers: u8 {
+        const SHIFT = 0b0001;
+        const ALT = 0b0010;
+        const CTRL = 0b0100;
+        const SUPER = 0b1000;
+    }
+}
+
+/// A mouse event, reported with zero-based coordinates where (0, 0) is the
+/// upper left cell of the terminal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MouseEvent {
+    pub action: MouseAction,
+    pub column: u16,
+    pub row: u16,
+    pub modifiers: Modifiers,
+}
+
+/// What happened to the mouse.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseAction {
+    /// A button was pressed. Wheel scrolling is reported as a press of
+    /// `MouseButton::WheelUp` or `MouseButton::WheelDown`.
+    Press(MouseButton),
+    /// A button was released.
+    ///
+    /// Terminals without SGR mouse support can't tell which button was
+    /// released, in that case `MouseButton::Left` is reported.
+    Release(MouseButton),
+    /// The mouse moved while a button was held down (termion calls this
+    /// `Hold`).
+    Drag(MouseButton),
+}
+
 /// A mouse button.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseButton {
@@ -32,11 +71,39 @@ pub enum MouseButton {
     WheelDown,
 }
 
+/// A key press: the key and the modifiers held down with it. Ctrl+C is
+/// `Key::Char('c')` with `Modifiers::CTRL`.
+///
+/// Terminals only report some combinations, e.g. Ctrl+Left isn't told apart
+/// from Left by every terminal, and Shift is usually folded into the character
+/// typed. Terminals supporting the kitty keyboard protocol report them all.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+}
+
+impl From<Key> for KeyEvent {
+    fn from(key: Key) -> Self {
+        Self::new(key, Modifiers::empty())
+    }
+}
+
 /// A key.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Key {
     /// Backspace.
     Backspace,
+    /// Enter key.
+    Enter,
+    /// Tab key, Shift+Tab is reported with `Modifiers::SHIFT`.
+    Tab,
     /// Left arrow.
     Left,
     /// Right arrow.
@@ -53,65 +120,106 @@ pub enum Key {
     PageUp,
     /// Page Down key.
     PageDown,
-    /// Backward Tab key.
-    BackTab,
     /// Delete key.
     Delete,
     /// Insert key.
     Insert,
-    /// Function keys.
-    ///
-    /// Only function keys 1 through 12 are supported.
+    /// Function keys, F1 to F35.
     F(u8),
     /// Normal character.
     Char(char),
-    /// Alt modified character.
-    Alt(char),
-    /// Ctrl modified character.
-    ///
-    /// Note that certain keys may not be modifiable with `ctrl`, due to limitations of terminals.
-    Ctrl(char),
-    /// Null byte.
-    Null,
     /// Esc key.
     Esc,
-    Unsupported,
 }
 
-impl From<termion::event::Key> for Key {
+impl From<termion::event::Key> for KeyEvent {
     fn from(key: termion::event::Key) -> Self {
-        match key {
-            termion::event::Key::Backspace => Self::Backspace,
-            termion::event::Key::Left => Self::Left,
-            termion::event::Key::Right => Self::Right,
-            termion::event::Key::Up => Self::Up,
-            termion::event::Key::Down => Self::Down,
-            termion::event::Key::Home => Self::Home,
-            termion::event::Key::End => Self::End,
-            termion::event::Key::PageUp => Self::PageUp,
-            termion::event::Key::PageDown => Self::PageDown,
-            termion::event::Key::BackTab => Self::BackTab,
-            termion::event::Key::Delete => Self::Delete,
-            termion::event::Key::Insert => Self::Insert,
-            termion::event::Key::F(f) => Self::F(f),
-            termion::event::Key::Char(c) => Self::Char(c),
-            termion::event::Key::Alt(c) => Self::Alt(c),
-            termion::event::Key::Ctrl(c) => Self::Ctrl(c),
-            termion::event::Key::Null => Self::Null,
-            termion::event::Key::Esc => Self::Esc,
-            termion::event::Key::__IsNotComplete => Self::Unsupported,
+        use termion::event::Key as K;
+
+        let (key, modifiers) = match key {
+            K::Backspace => (Key::Backspace, Modifiers::empty()),
+            K::Left => (Key::Left, Modifiers::empty()),
+            K::Right => (Key::Right, Modifiers::empty()),
+            K::Up => (Key::Up, Modifiers::empty()),
+            K::Down => (Key::Down, Modifiers::empty()),
+            K::Home => (Key::Home, Modifiers::empty()),
+            K::End => (Key::End, Modifiers::empty()),
+            K::PageUp => (Key::PageUp, Modifiers::empty()),
+            K::PageDown => (Key::PageDown, Modifiers::empty()),
+            K::BackTab => (Key::Tab, Modifiers::SHIFT),
+            K::Delete => (Key::Delete, Modifiers::empty()),
+            K::Insert => (Key::Insert, Modifiers::empty()),
+            K::F(f) => (Key::F(f), Modifiers::empty()),
+            K::Char('\n') => (Key::Enter, Modifiers::empty()),
+            K::Char('\t') => (Key::Tab, Modifiers::empty()),
+            K::Char(c) => (Key::Char(c), Modifiers::empty()),
+            K::Alt(c) => (Key::Char(c), Modifiers::ALT),
+            K::Ctrl(c) => (Key::Char(c), Modifiers::CTRL),
+            K::Null => (Key::Char(' '), Modifiers::CTRL),
+            K::Esc | K::__IsNotComplete => (Key::Esc, Modifiers::empty()),
+        };
+        Self::new(key, modifiers)
+    }
+}
+
+impl From<termion::event::MouseButton> for MouseButton {
+    fn from(button: termion::event::MouseButton) -> Self {
+        match button {
+            termion::event::MouseButton::Left => Self::Left,
+            termion::event::MouseButton::Right => Self::Right,
+            termion::event::MouseButton::Middle => Self::Middle,
+            termion::event::MouseButton::WheelUp => Self::WheelUp,
+            termion::event::MouseButton::WheelDown => Self::WheelDown,
+        }
+    }
+}
+
+impl From<termion::event::MouseEvent> for MouseEvent {
+    fn from(event: termion::event::MouseEvent) -> Self {
+        let (action, x, y) = match event {
+            termion::event::MouseEvent::Press(button, x, y) => {
+                (MouseAction::Press(button.into()), x, y)
+            }
+            termion::event::MouseEvent::Release(x, y) => {
+                (MouseAction::Release(MouseButton::Left), x, y)
+            }
+            termion::event::MouseEvent::Hold(x, y) => (MouseAction::Drag(MouseButton::Left), x, y),
+        };
+
+        Self {
+            action,
+            column: x.saturating_sub(1),
+            row: y.saturating_sub(1),
+            modifiers: Modifiers::empty(),
         }
     }
 }
 
-pub(crate) fn receive_inputs<MSG, I>(input: I, mut input_sender: ChannelSender<MSG>)
-where
+pub(crate) fn receive_inputs<R, MSG, I>(
+    mut reader: R,
+    input: I,
+    mut input_sender: ChannelSender<MSG>,
+) where
+    R: Read,
     I: Fn(InputEvent) -> Option<MSG> + Send + 'static,
 {
-    let stdin = stdin();
-    for key in stdin.keys().flatten() {
-        if let Some(msg) = input(InputEvent::Key(key.into())) {
-            input_sender.send(msg);
+    let mut parser = Parser::default();
+    let mut buf = [0; 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+
+        for event in parser.feed(&buf[..n]) {
+            if let Some(msg) = input(event) {
+                // the runtime is gone, the reader isn't needed anymore
+                if !input_sender.send(msg) {
+                    return;
+                }
+            }
         }
     }
 }
@@ -0,0 +1,109 @@
+use crate::input::KeyEvent;
+
+/// Binding ties one or more keys to an action, with the help shown for it. A
+/// disabled binding matches no key and is left out of the help.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Binding {
+    keys: Vec<KeyEvent>,
+    help: Help,
+    enabled: bool,
+}
+
+/// Help describes a binding: the keys as they should be shown, e.g. "↑/k",
+/// and what they do.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Help {
+    pub key: String,
+    pub desc: String,
+}
+
+impl Binding {
+    /// new creates an enabled binding for the keys, without help.
+    pub fn new(keys: Vec<KeyEvent>) -> Self {
+        Self {
+            keys,
+            help: Help::default(),
+            enabled: true,
+        }
+    }
+
+    pub fn with_help(mut self, key: &str, desc: &str) -> Self {
+        self.help = Help {
+            key: key.to_string(),
+            desc: desc.to_string(),
+        };
+        self
+    }
+
+    /// matches reports whether the event is one of the keys of the binding.
+    /// Modifiers must be the same, Ctrl+J doesn't match J.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        self.enabled && self.keys.contains(event)
+    }
+
+    pub fn keys(&self) -> &[KeyEvent] {
+        &self.keys
+    }
+
+    pub fn set_keys(&mut self, keys: Vec<KeyEvent>) {
+        self.keys = keys;
+    }
+
+    pub fn help(&self) -> &Help {
+        &self.help
+    }
+
+    pub fn set_help(&mut self, key: &str, desc: &str) {
+        self.help = Help {
+            key: key.to_string(),
+            desc: desc.to_string(),
+        };
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled && !self.keys.is_empty()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+/// KeyMap is implemented by the key maps of components and programs, to list
+/// their bindings in the help component.
+pub trait KeyMap {
+    /// short_help returns the bindings shown on a single line.
+    fn short_help(&self) -> Vec<&Binding>;
+
+    /// full_help returns all the bindings, each group is shown as a column.
+    fn full_help(&self) -> Vec<Vec<&Binding>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Key, Modifiers};
+
+    #[test]
+    fn matches_keys_with_the_same_modifiers() {
+        let ctrl_j = KeyEvent::new(Key::Char('j'), Modifiers::CTRL);
+        let binding = Binding::new(vec![Key::Down.into(), ctrl_j]);
+        assert!(binding.matches(&Key::Down.into()));
+        assert!(binding.matches(&ctrl_j));
+        assert!(!binding.matches(&Key::Char('j').into()));
+        assert!(!binding.matches(&KeyEvent::new(Key::Down, Modifiers::SHIFT)));
+    }
+
+    #[test]
+    fn disabled_bindings_match_nothing() {
+        let mut binding = Binding::new(vec![Key::Enter.into()]);
+        binding.set_enabled(false);
+        assert!(!binding.enabled());
+        assert!(!binding.matches(&Key::Enter.into()));
+
+        binding.set_enabled(true);
+        assert!(binding.enabled());
+        binding.set_keys(vec![]);
+        assert!(!binding.enabled());
+    }
+}
@@ -0,0 +1,282 @@
+/// Rect is an area of the screen, in cells from the top left corner.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// inner is the area left inside a margin on all sides.
+    pub fn inner(&self, margin: u16) -> Self {
+        let margin = margin.min(self.width / 2).min(self.height / 2);
+        Self {
+            x: self.x + margin,
+            y: self.y + margin,
+            width: self.width - 2 * margin,
+            height: self.height - 2 * margin,
+        }
+    }
+
+    pub fn area(&self) -> u32 {
+        u32::from(self.width) * u32::from(self.height)
+    }
+}
+
+/// Direction is the axis a layout splits along: rows stacked vertically or
+/// columns side by side.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Vertical,
+    Horizontal,
+}
+
+/// Constraint sets the size of a region of a layout, along its direction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Constraint {
+    /// Length is a fixed number of cells.
+    Length(u16),
+    /// Percentage is a part of the room of the layout, out of 100.
+    Percentage(u16),
+    /// Ratio is a part of the room of the layout, e.g. Ratio(1, 3) for a
+    /// third.
+    Ratio(u32, u32),
+    /// Min takes at least the cells given, and the room left when there's
+    /// no Fill region.
+    Min(u16),
+    /// Max takes the room left, up to the cells given.
+    Max(u16),
+    /// Fill takes the room left, shared between the Fill regions by weight.
+    Fill(u16),
+}
+
+/// Layout splits a rectangle into regions, one per constraint:
+///
+/// ```
+/// use moonlight::layout::{Constraint, Layout, Rect};
+/// use std::convert::TryInto;
+///
+/// let [header, body, footer]: [Rect; 3] = Layout::vertical(vec![
+///     Constraint::Length(3),
+///     Constraint::Fill(1),
+///     Constraint::Length(1),
+/// ])
+/// .split(Rect::new(0, 0, 80, 24))
+/// .try_into()
+/// .unwrap();
+/// assert_eq!(body, Rect::new(0, 3, 80, 20));
+/// ```
+///
+/// Layouts are cheap, split them again with the new size when the window is
+/// resized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Layout {
+    direction: Direction,
+    constraints: Vec<Constraint>,
+    margin: u16,
+    spacing: u16,
+}
+
+impl Layout {
+    pub fn new(direction: Direction, constraints: Vec<Constraint>) -> Self {
+        Self {
+            direction,
+            constraints,
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    /// vertical stacks the regions from top to bottom.
+    pub fn vertical(constraints: Vec<Constraint>) -> Self {
+        Self::new(Direction::Vertical, constraints)
+    }
+
+    /// horizontal puts the regions side by side from left to right.
+    pub fn horizontal(constraints: Vec<Constraint>) -> Self {
+        Self::new(Direction::Horizontal, constraints)
+    }
+
+    /// Leave cells on all sides of the rectangle before splitting it.
+    pub fn with_margin(mut self, margin: u16) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Leave cells between the regions.
+    pub fn with_spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// split returns a region of the area for each constraint. When the
+    /// constraints ask for more room than there is, the last regions are
+    /// shrunk first, down to nothing. Room nobody takes is left at the end.
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let area = area.inner(self.margin);
+        let (start, room) = match self.direction {
+            Direction::Vertical => (area.y, area.height),
+            Direction::Horizontal => (area.x, area.width),
+        };
+        let gaps = self.spacing as usize * self.constraints.len().saturating_sub(1);
+        let room = (room as usize).saturating_sub(gaps);
+
+        let mut position = start as usize;
+        sizes(&self.constraints, room)
+            .into_iter()
+            .map(|size| {
+                let (offset, size) = (position as u16, size as u16);
+                position += size as usize + self.spacing as usize;
+                match self.direction {
+                    Direction::Vertical => Rect::new(area.x, offset, area.width, size),
+                    Direction::Horizontal => Rect::new(offset, area.y, size, area.height),
+                }
+            })
+            .collect()
+    }
+}
+
+// The size of each region in the room.
+fn sizes(constraints: &[Constraint], room: usize) -> Vec<usize> {
+    let mut sizes: Vec<usize> = constraints
+        .iter()
+        .map(|constraint| match *constraint {
+            Constraint::Length(n) | Constraint::Min(n) => n as usize,
+            Constraint::Percentage(p) => room * (p.min(100) as usize) / 100,
+            Constraint::Ratio(_, 0) => 0,
+            Constraint::Ratio(a, b) => room * (a.min(b) as usize) / b as usize,
+            Constraint::Max(_) | Constraint::Fill(_) => 0,
+        })
+        .collect();
+
+    // too much asked, cut from the end
+    let mut over = sizes.iter().sum::<usize>().saturating_sub(room);
+    for size in sizes.iter_mut().rev() {
+        let cut = over.min(*size);
+        *size -= cut;
+        over -= cut;
+    }
+
+    let mut left = room.saturating_sub(sizes.iter().sum());
+    for (size, constraint) in sizes.iter_mut().zip(constraints) {
+        if let Constraint::Max(n) = *constraint {
+            *size = left.min(n as usize);
+            left -= *size;
+        }
+    }
+
+    let weight = |constraint: &Constraint| match *constraint {
+        Constraint::Fill(weight) => weight as usize,
+        _ => 0,
+    };
+    let has_fill = constraints.iter().any(|c| matches!(c, Constraint::Fill(_)));
+    let weights: Vec<usize> = if has_fill {
+        constraints.iter().map(weight).collect()
+    } else {
+        let min = |c: &Constraint| matches!(c, Constraint::Min(_)) as usize;
+        constraints.iter().map(min).collect()
+    };
+    share(&mut sizes, &weights, left);
+
+    sizes
+}
+
+// Share the room between the sizes by weight, rounding so it adds up.
+fn share(sizes: &mut [usize], weights: &[usize], room: usize) {
+    let total: usize = weights.iter().sum();
+    if total == 0 {
+        return;
+    }
+    let (mut weight, mut given) = (0, 0);
+    for (size, w) in sizes.iter_mut().zip(weights) {
+        weight += w;
+        let upto = (room * weight + total / 2) / total;
+        *size += upto - given;
+        given = upto;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Constraint::*;
+
+    fn heights(constraints: Vec<Constraint>, height: u16) -> Vec<u16> {
+        Layout::vertical(constraints)
+            .split(Rect::new(0, 0, 10, height))
+            .iter()
+            .map(|rect| rect.height)
+            .collect()
+    }
+
+    #[test]
+    fn cuts_the_last_regions_when_overfilled() {
+        let rects =
+            Layout::vertical(vec![Length(5), Length(5), Length(5)]).split(Rect::new(0, 0, 10, 12));
+        assert_eq!(
+            rects,
+            [
+                Rect::new(0, 0, 10, 5),
+                Rect::new(0, 5, 10, 5),
+                Rect::new(0, 10, 10, 2),
+            ]
+        );
+        assert_eq!(heights(vec![Percentage(60), Percentage(60)], 10), [6, 4]);
+        assert_eq!(heights(vec![Length(8), Min(4), Fill(1)], 10), [8, 2, 0]);
+    }
+
+    #[test]
+    fn gives_the_room_left_when_underfilled() {
+        // nobody takes it, it stays at the end
+        assert_eq!(heights(vec![Length(3), Length(4)], 10), [3, 4]);
+        assert_eq!(heights(vec![Percentage(50), Min(2), Max(3)], 10), [5, 2, 3]);
+        assert_eq!(
+            heights(vec![Percentage(50), Min(2), Max(3)], 20),
+            [10, 7, 3]
+        );
+        assert_eq!(heights(vec![Length(2), Min(2), Fill(1)], 10), [2, 2, 6]);
+        assert_eq!(
+            heights(vec![Ratio(1, 3), Ratio(1, 0), Fill(1)], 9),
+            [3, 0, 6]
+        );
+    }
+
+    #[test]
+    fn shares_the_remainder_by_weight() {
+        assert_eq!(heights(vec![Length(2), Fill(1), Fill(2)], 11), [2, 3, 6]);
+        assert_eq!(heights(vec![Fill(1), Fill(1), Fill(1)], 10), [3, 4, 3]);
+        assert_eq!(heights(vec![Min(0), Min(0), Min(0)], 2), [1, 0, 1]);
+    }
+
+    #[test]
+    fn splits_zero_size_areas() {
+        let rects = Layout::vertical(vec![Length(1), Fill(1)]).split(Rect::new(2, 3, 0, 0));
+        assert_eq!(rects, [Rect::new(2, 3, 0, 0), Rect::new(2, 3, 0, 0)]);
+
+        let rects = Layout::horizontal(vec![Min(1), Min(1)])
+            .with_spacing(1)
+            .split(Rect::new(0, 0, 0, 4));
+        assert!(rects.iter().all(|rect| rect.area() == 0));
+        assert_eq!(Layout::vertical(vec![]).split(Rect::new(0, 0, 5, 5)), []);
+    }
+
+    #[test]
+    fn leaves_margins_and_spacing() {
+        let rects = Layout::horizontal(vec![Fill(1), Fill(1)])
+            .with_margin(1)
+            .with_spacing(2)
+            .split(Rect::new(0, 0, 12, 5));
+        assert_eq!(rects, [Rect::new(1, 1, 4, 3), Rect::new(7, 1, 4, 3)]);
+        assert_eq!(Rect::new(0, 0, 3, 3).inner(5), Rect::new(1, 1, 1, 1));
+    }
+}
@@ -1,12 +1,23 @@
+pub mod ansi;
+pub mod backend;
+pub mod border;
 pub mod channels;
 pub mod color;
 pub mod commands;
-pub mod heartbeat;
+mod executor;
+mod guard;
+pub mod harness;
+mod heartbeat;
 pub mod input;
+pub mod keymap;
+pub mod layout;
+mod parser;
 mod render_channel;
 pub mod renderer;
 pub mod runtime;
 pub mod store;
+pub mod style;
+pub mod subscriptions;
 
 pub use channels::*;
 pub use color::*;
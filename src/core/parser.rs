@@ -0,0 +1,575 @@
+use std::str;
+
+use super::input::{InputEvent, Key, KeyEvent, Modifiers, MouseAction, MouseButton, MouseEvent};
+
+const ESC: u8 = 0x1B;
+
+// Bracketed paste wraps pasted text between these sequences.
+const PASTE_START: u32 = 200;
+const PASTE_END: &[u8] = b"\x1B[201~";
+
+/// Parser turns the bytes read from the terminal into input events. A read
+/// may end in the middle of an escape sequence, what's left of it is kept
+/// until the next read.
+#[derive(Default)]
+pub(crate) struct Parser {
+    pending: Vec<u8>,
+    paste: Option<Vec<u8>>, // bytes pasted so far
+}
+
+// What was found at the start of the pending bytes.
+enum Token {
+    Event(InputEvent, usize),
+    PasteStart(usize),
+    Skip(usize), // a sequence we don't support
+    Incomplete,
+}
+
+impl Parser {
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> Vec<InputEvent> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut events = vec![];
+        let mut start = 0;
+        while start < self.pending.len() {
+            let buf = &self.pending[start..];
+
+            // pasted text is delivered at once instead of key by key
+            if let Some(pasted) = self.paste.as_mut() {
+                match find(buf, PASTE_END) {
+                    Some(end) => {
+                        pasted.extend_from_slice(&buf[..end]);
+                        start += end + PASTE_END.len();
+                        let text = paste_text(&self.paste.take().unwrap_or_default());
+                        events.push(InputEvent::Paste(text));
+                    }
+                    None => {
+                        // keep what may be the beginning of the end marker
+                        let keep = partial_suffix(buf, PASTE_END);
+                        pasted.extend_from_slice(&buf[..buf.len() - keep]);
+                        start += buf.len() - keep;
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            match parse(buf) {
+                Token::Event(event, n) => {
+                    events.push(event);
+                    start += n;
+                }
+                Token::PasteStart(n) => {
+                    self.paste = Some(vec![]);
+                    start += n;
+                }
+                Token::Skip(n) => start += n,
+                // Escape and Alt+[ or Alt+O also start sequences, they're told
+                // apart by ending the read
+                Token::Incomplete => {
+                    match buf {
+                        [ESC] => events.push(key(Key::Esc, Modifiers::empty())),
+                        [ESC, b @ (b'[' | b'O')] => {
+                            events.push(key(Key::Char(*b as char), Modifiers::ALT))
+                        }
+                        _ => break,
+                    }
+                    start = self.pending.len();
+                }
+            }
+        }
+
+        self.pending.drain(..start);
+        events
+    }
+}
+
+fn key(key: Key, modifiers: Modifiers) -> InputEvent {
+    InputEvent::Key(KeyEvent::new(key, modifiers))
+}
+
+fn parse(buf: &[u8]) -> Token {
+    match buf {
+        [] | [ESC] => Token::Incomplete,
+        [ESC, b'[', ..] => parse_csi(buf),
+        [ESC, b'O', ..] => parse_ss3(buf),
+        [ESC, ESC, ..] => Token::Event(key(Key::Esc, Modifiers::empty()), 1),
+        // Alt sends an escape before the key
+        [ESC, rest @ ..] => match parse_char(rest) {
+            Token::Event(InputEvent::Key(event), n) => {
+                Token::Event(key(event.key, event.modifiers | Modifiers::ALT), n + 1)
+            }
+            token => token,
+        },
+        _ => parse_char(buf),
+    }
+}
+
+// A character or a control code, which is Ctrl with a key.
+fn parse_char(buf: &[u8]) -> Token {
+    let b = buf[0];
+    let (code, modifiers) = match b {
+        0x00 => (Key::Char(' '), Modifiers::CTRL),
+        b'\t' | b'\r' | b'\n' | 0x7F => (char_key(b), Modifiers::empty()),
+        0x01..=0x1A => (Key::Char((b - 1 + b'a') as char), Modifiers::CTRL),
+        0x1C..=0x1F => (Key::Char((b - 0x1C + b'4') as char), Modifiers::CTRL),
+        _ => return parse_utf8(buf),
+    };
+    Token::Event(key(code, modifiers), 1)
+}
+
+// The key of a single byte, see parse_char for control codes.
+fn char_key(b: u8) -> Key {
+    match b {
+        b'\t' => Key::Tab,
+        b'\r' | b'\n' => Key::Enter,
+        0x7F => Key::Backspace,
+        b => Key::Char(b as char),
+    }
+}
+
+fn parse_utf8(buf: &[u8]) -> Token {
+    let len = match buf[0] {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Token::Skip(1),
+    };
+    if buf.len() < len {
+        return Token::Incomplete;
+    }
+    match str::from_utf8(&buf[..len])
+        .ok()
+        .and_then(|s| s.chars().next())
+    {
+        Some(c) => Token::Event(key(Key::Char(c), Modifiers::empty()), len),
+        None => Token::Skip(1),
+    }
+}
+
+// SS3 sequences, `ESC O x`, sent for the arrows in application mode and F1 to
+// F4.
+fn parse_ss3(buf: &[u8]) -> Token {
+    if buf.len() < 3 {
+        return Token::Incomplete;
+    }
+    match letter_key(buf[2]) {
+        Some(code) => Token::Event(key(code, Modifiers::empty()), 3),
+        None => Token::Skip(3),
+    }
+}
+
+// Keys ending a sequence with a letter, like `ESC [ 1 ; 5 D` for Ctrl+Left.
+fn letter_key(b: u8) -> Option<Key> {
+    Some(match b {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'P'..=b'S' => Key::F(b - b'P' + 1),
+        _ => return None,
+    })
+}
+
+// CSI sequences, `ESC [` followed by parameters and a final byte.
+fn parse_csi(buf: &[u8]) -> Token {
+    match buf.get(2) {
+        None => return Token::Incomplete,
+        // X10 mouse reports are followed by three raw bytes
+        Some(b'M') => return parse_x10_mouse(buf),
+        // the linux console sends `ESC [ [ A` to `ESC [ [ E` for F1 to F5
+        Some(b'[') => {
+            return match buf.get(3) {
+                None => Token::Incomplete,
+                Some(b @ b'A'..=b'E') => {
+                    Token::Event(key(Key::F(b - b'A' + 1), Modifiers::empty()), 4)
+                }
+                Some(_) => Token::Skip(4),
+            }
+        }
+        _ => {}
+    }
+
+    let mut end = 2;
+    while let Some(0x20..=0x3F) = buf.get(end) {
+        end += 1;
+    }
+    let last = match buf.get(end) {
+        None => return Token::Incomplete,
+        Some(b @ 0x40..=0x7E) => *b,
+        Some(_) => return Token::Skip(end), // broken sequence
+    };
+    let params = &buf[2..end];
+    let len = end + 1;
+
+    if params.first() == Some(&b'<') {
+        return match parse_sgr_mouse(&buf[..len]) {
+            Some(mouse) => Token::Event(InputEvent::Mouse(mouse), len),
+            None => Token::Skip(len),
+        };
+    }
+    // private sequences are replies to queries we don't send
+    if let Some(b'<'..=b'?') = params.first() {
+        return Token::Skip(len);
+    }
+
+    let params = match parse_params(params) {
+        Some(params) => params,
+        None => return Token::Skip(len),
+    };
+    let param = |i: usize| params.get(i).copied().flatten();
+    let modifiers = modifiers(param(1));
+
+    let code = match (last, param(0)) {
+        (b'I', None) => return Token::Event(InputEvent::FocusGained, len),
+        (b'O', None) => return Token::Event(InputEvent::FocusLost, len),
+        (b'Z', _) => return Token::Event(key(Key::Tab, modifiers | Modifiers::SHIFT), len),
+        (b'~', Some(PASTE_START)) => return Token::PasteStart(len),
+        // xterm's modifyOtherKeys, `ESC [ 27 ; modifiers ; code ~`
+        (b'~', Some(27)) => param(2).and_then(code_key),
+        (b'~', Some(n)) => tilde_key(n),
+        // the kitty keyboard protocol, `ESC [ code ; modifiers u`
+        (b'u', Some(code)) => code_key(code),
+        (b, _) => letter_key(b),
+    };
+
+    match code {
+        Some(code) => Token::Event(key(code, modifiers), len),
+        None => Token::Skip(len),
+    }
+}
+
+// Parameters are separated by ';', a parameter may have sub parameters after
+// ':' which we don't use. Missing parameters are None.
+fn parse_params(params: &[u8]) -> Option<Vec<Option<u32>>> {
+    str::from_utf8(params)
+        .ok()?
+        .split(';')
+        .map(|param| match param.split(':').next().unwrap_or("") {
+            "" => Some(None),
+            n => n.parse().ok().map(Some),
+        })
+        .collect()
+}
+
+// Modifiers are sent as one plus the bits of the keys held down.
+fn modifiers(param: Option<u32>) -> Modifiers {
+    let bits = param.unwrap_or(1).saturating_sub(1);
+    Modifiers::from_bits_truncate(bits as u8)
+}
+
+// Keys of `ESC [ n ~` sequences.
+fn tilde_key(n: u32) -> Option<Key> {
+    Some(match n {
+        1 | 7 => Key::Home,
+        2 => Key::Insert,
+        3 => Key::Delete,
+        4 | 8 => Key::End,
+        5 => Key::PageUp,
+        6 => Key::PageDown,
+        11..=15 => Key::F(n as u8 - 10),
+        17..=21 => Key::F(n as u8 - 11),
+        23..=26 => Key::F(n as u8 - 12),
+        28 | 29 => Key::F(n as u8 - 13),
+        31..=34 => Key::F(n as u8 - 14),
+        _ => return None,
+    })
+}
+
+// Keys by unicode code point, the kitty protocol puts the keys which have none
+// in the private use area.
+fn code_key(code: u32) -> Option<Key> {
+    Some(match code {
+        9 => Key::Tab,
+        13 => Key::Enter,
+        27 => Key::Esc,
+        8 | 127 => Key::Backspace,
+        57376..=57398 => Key::F((code - 57376) as u8 + 13),
+        // keypad
+        57399..=57408 => Key::Char((b'0' + (code - 57399) as u8) as char),
+        57409 => Key::Char('.'),
+        57410 => Key::Char('/'),
+        57411 => Key::Char('*'),
+        57412 => Key::Char('-'),
+        57413 => Key::Char('+'),
+        57414 => Key::Enter,
+        57415 => Key::Char('='),
+        57417 => Key::Left,
+        57418 => Key::Right,
+        57419 => Key::Up,
+        57420 => Key::Down,
+        57421 => Key::PageUp,
+        57422 => Key::PageDown,
+        57423 => Key::Home,
+        57424 => Key::End,
+        57425 => Key::Insert,
+        57426 => Key::Delete,
+        0..=31 | 57344..=63743 => return None,
+        _ => Key::Char(char::from_u32(code)?),
+    })
+}
+
+/// parse_x10_mouse decodes a legacy mouse report, `ESC [ M Cb Cx Cy` with each
+/// value offset by 32. Releases don't tell which button was released.
+fn parse_x10_mouse(buf: &[u8]) -> Token {
+    if buf.len() < 6 {
+        return Token::Incomplete;
+    }
+    let cb = buf[3].saturating_sub(32);
+    let x = u16::from(buf[4].saturating_sub(32));
+    let y = u16::from(buf[5].saturating_sub(32));
+
+    // motion reports have bit 32 set, like in SGR reports
+    let motion = cb & 32 != 0;
+    let held = |button| match motion {
+        true => MouseAction::Drag(button),
+        false => MouseAction::Press(button),
+    };
+    let action = match (cb & 0b11, cb & 64 != 0) {
+        (0, true) => MouseAction::Press(MouseButton::WheelUp),
+        (1, true) => MouseAction::Press(MouseButton::WheelDown),
+        (0, false) => held(MouseButton::Left),
+        (1, false) => held(MouseButton::Middle),
+        (2, false) => held(MouseButton::Right),
+        (3, false) if !motion => MouseAction::Release(MouseButton::Left),
+        _ => return Token::Skip(6), // motion without any button held
+    };
+
+    let mouse = MouseEvent {
+        action,
+        column: x.saturating_sub(1),
+        row: y.saturating_sub(1),
+        modifiers: mouse_modifiers(u16::from(cb)),
+    };
+    Token::Event(InputEvent::Mouse(mouse), 6)
+}
+
+fn mouse_modifiers(cb: u16) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    if cb & 4 != 0 {
+        modifiers |= Modifiers::SHIFT;
+    }
+    if cb & 8 != 0 {
+        modifiers |= Modifiers::ALT;
+    }
+    if cb & 16 != 0 {
+        modifiers |= Modifiers::CTRL;
+    }
+    modifiers
+}
+
+/// parse_sgr_mouse decodes an SGR (1006) mouse report, `ESC [ < Cb ; Cx ; Cy M`
+/// for presses and motion or the same sequence ending in `m` for releases.
+fn parse_sgr_mouse(raw: &[u8]) -> Option<MouseEvent> {
+    let body = raw.strip_prefix(b"\x1B[<")?;
+    let (last, params) = body.split_last()?;
+    let mut params = str::from_utf8(params)
+        .ok()?
+        .split(';')
+        .map(|n| n.parse::<u16>().ok());
+
+    let cb = params.next()??;
+    let x = params.next()??;
+    let y = params.next()??;
+
+    let button = if cb & 64 != 0 {
+        match cb & 0b11 {
+            0 => MouseButton::WheelUp,
+            1 => MouseButton::WheelDown,
+            _ => return None,
+        }
+    } else {
+        match cb & 0b11 {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            _ => return None, // motion without any button held
+        }
+    };
+
+    let action = match (*last, cb & 32 != 0) {
+        (b'M', true) => MouseAction::Drag(button),
+        (b'M', false) => MouseAction::Press(button),
+        (b'm', _) => MouseAction::Release(button),
+        _ => return None,
+    };
+
+    Some(MouseEvent {
+        action,
+        column: x.saturating_sub(1),
+        row: y.saturating_sub(1),
+        modifiers: mouse_modifiers(cb),
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// The length of the longest end of buf which is a beginning of marker.
+fn partial_suffix(buf: &[u8], marker: &[u8]) -> usize {
+    (1..marker.len())
+        .rev()
+        .find(|&n| buf.ends_with(&marker[..n]))
+        .unwrap_or(0)
+}
+
+// Terminals send line breaks as carriage returns.
+fn paste_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mouse(action: MouseAction, column: u16, row: u16) -> InputEvent {
+        InputEvent::Mouse(MouseEvent {
+            action,
+            column,
+            row,
+            modifiers: Modifiers::empty(),
+        })
+    }
+
+    #[test]
+    fn parses_sgr_mouse_reports() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1B[<0;10;5M\x1B[<32;11;5M\x1B[<0;11;5m\x1B[<65;1;1M"),
+            [
+                mouse(MouseAction::Press(MouseButton::Left), 9, 4),
+                mouse(MouseAction::Drag(MouseButton::Left), 10, 4),
+                mouse(MouseAction::Release(MouseButton::Left), 10, 4),
+                mouse(MouseAction::Press(MouseButton::WheelDown), 0, 0),
+            ]
+        );
+        // motion without any button held is dropped
+        assert_eq!(parser.feed(b"\x1B[<35;3;3M"), []);
+    }
+
+    #[test]
+    fn parses_x10_mouse_reports() {
+        let mut parser = Parser::default();
+        let report = |cb: u8, x: u8, y: u8| vec![ESC, b'[', b'M', cb + 32, x + 32, y + 32];
+        let bytes = [
+            report(2, 10, 5),
+            report(32 + 2, 11, 5),
+            report(3, 11, 5),
+            report(64, 1, 1),
+            report(32 + 3, 1, 1),
+        ]
+        .concat();
+        assert_eq!(
+            parser.feed(&bytes),
+            [
+                mouse(MouseAction::Press(MouseButton::Right), 9, 4),
+                mouse(MouseAction::Drag(MouseButton::Right), 10, 4),
+                mouse(MouseAction::Release(MouseButton::Left), 10, 4),
+                mouse(MouseAction::Press(MouseButton::WheelUp), 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_csi_keys_with_modifiers() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1B[A\x1B[1;5D\x1B[3;2~\x1B[15;7~\x1B[Z\x1B[[B"),
+            [
+                key(Key::Up, Modifiers::empty()),
+                key(Key::Left, Modifiers::CTRL),
+                key(Key::Delete, Modifiers::SHIFT),
+                key(Key::F(5), Modifiers::CTRL | Modifiers::ALT),
+                key(Key::Tab, Modifiers::SHIFT),
+                key(Key::F(2), Modifiers::empty()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_kitty_keys() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1B[97;5u\x1B[13u\x1B[97:65;2u\x1B[57376;9u\x1B[57399u"),
+            [
+                key(Key::Char('a'), Modifiers::CTRL),
+                key(Key::Enter, Modifiers::empty()),
+                key(Key::Char('a'), Modifiers::SHIFT),
+                key(Key::F(13), Modifiers::SUPER),
+                key(Key::Char('0'), Modifiers::empty()),
+            ]
+        );
+        // modifier keys on their own are dropped
+        assert_eq!(parser.feed(b"\x1B[57441;2u"), []);
+    }
+
+    #[test]
+    fn parses_modify_other_keys() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1B[27;5;105~\x1B[27;3;13~"),
+            [
+                key(Key::Char('i'), Modifiers::CTRL),
+                key(Key::Enter, Modifiers::ALT),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_ss3_keys() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1BOA\x1BOH\x1BOP\x1BOS"),
+            [
+                key(Key::Up, Modifiers::empty()),
+                key(Key::Home, Modifiers::empty()),
+                key(Key::F(1), Modifiers::empty()),
+                key(Key::F(4), Modifiers::empty()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_bracketed_paste() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"\x1B[200~hello\r\nworld\x1B[A\x1B[201~x"),
+            [
+                InputEvent::Paste(String::from("hello\nworld\x1B[A")),
+                key(Key::Char('x'), Modifiers::empty()),
+            ]
+        );
+
+        // the paste and its end marker may come in several reads
+        assert_eq!(parser.feed(b"\x1B[200~ab"), []);
+        assert_eq!(parser.feed(b"c\x1B[20"), []);
+        assert_eq!(parser.feed(b"1~"), [InputEvent::Paste(String::from("abc"))]);
+    }
+
+    #[test]
+    fn keeps_sequences_split_across_reads() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            parser.feed(b"a\x1B[1"),
+            [key(Key::Char('a'), Modifiers::empty())]
+        );
+        assert_eq!(parser.feed(b";5"), []);
+        assert_eq!(parser.feed(b"C"), [key(Key::Right, Modifiers::CTRL)]);
+
+        assert_eq!(parser.feed(b"\x1BO"), [key(Key::Char('O'), Modifiers::ALT)]);
+        assert_eq!(parser.feed(&"é".as_bytes()[..1]), []);
+        assert_eq!(
+            parser.feed(&"é".as_bytes()[1..]),
+            [key(Key::Char('é'), Modifiers::empty())]
+        );
+        assert_eq!(parser.feed(b"\x1B"), [key(Key::Esc, Modifiers::empty())]);
+    }
+}
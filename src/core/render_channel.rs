@@ -1,49 +1,30 @@
-use std::{
-    mem,
-    sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc, Once,
-    },
-};
+use std::sync::mpsc::{channel, Receiver, Sender};
 
 #[derive(Clone)]
-pub struct NextFrame;
+pub enum NextFrame {
+    Render,
+    // the terminal was resized, its size must be read again from the backend
+    Resize,
+}
 
+/// RenderChannel wakes up the main loop of the runtime it was created for,
+/// either to render a new frame or to handle a resize.
 #[derive(Clone)]
 pub struct RenderChannel {
-    pub sender: Sender<NextFrame>,
-    pub receiver: Arc<Receiver<NextFrame>>,
+    sender: Sender<NextFrame>,
 }
 
 impl RenderChannel {
-    pub fn new() -> Self {
-        singleton()
-    }
-
-    pub fn sender(&self) -> Sender<NextFrame> {
-        self.sender.clone()
+    pub fn new() -> (Self, Receiver<NextFrame>) {
+        let (sender, receiver) = channel();
+        (Self { sender }, receiver)
     }
 
     pub fn next_frame(&self) {
-        self.sender.send(NextFrame);
+        self.sender.send(NextFrame::Render).ok();
     }
-}
-
-fn singleton() -> RenderChannel {
-    static mut RENDER_SCHEDULE: *const RenderChannel = 0 as *const RenderChannel;
-    static ONCE: Once = Once::new();
-
-    unsafe {
-        ONCE.call_once(|| {
-            let (tx, rx) = channel();
-            let singleton = RenderChannel {
-                sender: tx,
-                receiver: Arc::new(rx),
-            };
-
-            RENDER_SCHEDULE = mem::transmute(Box::new(singleton));
-        });
 
-        (*RENDER_SCHEDULE).clone()
+    pub fn resize(&self) {
+        self.sender.send(NextFrame::Resize).ok();
     }
 }
@@ -1,13 +1,18 @@
-use std::io::{stdout, Result, Stdout, Write};
+use std::io::{Result, Write};
 
-use termion::raw::{IntoRawMode, RawTerminal};
+use crate::{
+    backend::{Backend, SharedBackend},
+    commands, Cmd,
+};
 
-use crate::Cmd;
+use super::ansi::truncate;
 
 const ESC: &str = "\x1B[";
 
-static mut LINES_RENDERED: i32 = 0;
-
+/// RenderMsg is handled by the renderer itself instead of the reducer, see
+/// `Runtime::with_render_messages`. Boundaries are zero-based screen rows,
+/// the top one included and the bottom one excluded, so these only make
+/// sense in fullscreen where the first line of the view is the first row.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RenderMsg {
     SyncScrollArea {
@@ -28,93 +33,386 @@ pub enum RenderMsg {
 }
 
 pub struct Renderer {
-    stdout: RawTerminal<Stdout>,
+    backend: SharedBackend,
+    width: u16,
+    height: u16,
+    last_frame: Vec<String>, // lines rendered in the last frame
+    repaint: bool,
+    scroll_area: Option<(usize, usize)>, // lines left out of regular repaints
 }
 
 impl Renderer {
-    pub(crate) fn new() -> Self {
-        let stdout = stdout().into_raw_mode().unwrap();
-        Self { stdout }
+    pub(crate) fn new(backend: Box<dyn Backend>) -> Self {
+        let backend = SharedBackend::new(backend);
+        let (width, height) = backend.size().unwrap_or((0, 0));
+        Self {
+            backend,
+            width,
+            height,
+            last_frame: vec![],
+            repaint: false,
+            scroll_area: None,
+        }
+    }
+
+    pub(crate) fn backend(&self) -> SharedBackend {
+        self.backend.clone()
     }
 
+    pub(crate) fn size(&self) -> Result<(u16, u16)> {
+        self.backend.size()
+    }
+
+    // Read the terminal size again after a resize. Lines may have been
+    // reflowed by the terminal, so the next frame is repainted entirely.
+    pub(crate) fn resize(&mut self) -> Result<(u16, u16)> {
+        let (width, height) = self.backend.size()?;
+        self.width = width;
+        self.height = height;
+        self.repaint = true;
+        Ok((width, height))
+    }
+
+    // Render a view, only the lines which changed since the last frame are
+    // written. The cursor is left on the last line of the frame.
     pub(crate) fn render(&mut self, view: &str) -> Result<()> {
-        let mut view = view.to_owned() + "\n";
+        let mut lines: Vec<String> = view
+            .split('\n')
+            .map(|line| self.fit(line.trim_end_matches('\r')))
+            .collect();
+
+        // lines scrolled out of the screen can't be reached anymore, only the
+        // bottom of a view taller than the terminal is rendered
+        let height = self.height as usize;
+        if height > 0 && lines.len() > height {
+            lines.drain(..lines.len() - height);
+        }
 
-        // We need to add carriage returns to ensure that the cursor travels to the
-        // start of a column after a newline
-        view = view.replace("\n", "\r\n");
+        if !self.repaint && lines == self.last_frame {
+            return Ok(());
+        }
+
+        let mut buf = String::new();
 
-        unsafe {
-            if LINES_RENDERED > 0 {
-                self.clear_lines(LINES_RENDERED)?;
+        // go back to the first line of the last frame
+        if self.last_frame.len() > 1 {
+            buf += &format!("{}{}F", ESC, self.last_frame.len() - 1);
+        } else {
+            buf += "\r";
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                buf += "\r\n";
+            }
+
+            if self.in_scroll_area(i) {
+                continue;
             }
-            LINES_RENDERED = 0;
+
+            if self.repaint || self.last_frame.get(i) != Some(line) {
+                buf += ESC;
+                buf += "2K";
+                buf += line;
+            }
+        }
+
+        // the new frame is shorter, erase the leftovers below it
+        if lines.len() < self.last_frame.len() {
+            buf += "\r\n";
+            buf += ESC;
+            buf += "J";
+            buf += ESC;
+            buf += "1A";
         }
 
-        write!(self.stdout, "{}", view)?; // render
-        self.stdout.flush().unwrap();
+        write!(self.backend, "{}", buf)?;
+        self.backend.flush()?;
+
+        self.last_frame = lines;
+        self.repaint = false;
+        Ok(())
+    }
+
+    // Handle a high-performance rendering message. The lines of a scroll area
+    // are owned by these messages: they are written through a scroll region
+    // (DECSTBM) with insert/delete line sequences, so scrolling only writes
+    // the lines which came into view, and regular frames leave them alone.
+    pub(crate) fn handle(&mut self, msg: RenderMsg) -> Result<()> {
+        let mut buf = String::new();
 
-        unsafe {
-            LINES_RENDERED = view.matches("\r\n").count() as i32;
+        match msg {
+            RenderMsg::SyncScrollArea {
+                lines,
+                top_boundary,
+                bottom_boundary,
+            } => {
+                let (top, bottom) = boundaries(top_boundary, bottom_boundary);
+                self.scroll_area = Some((top, bottom));
+                buf += &set_scroll_region(top, bottom);
+                for row in top..bottom {
+                    let line = lines.get(row - top).map(String::as_str).unwrap_or("");
+                    buf += &format!("{}{};1H{}2K{}", ESC, row + 1, ESC, self.fit(line));
+                }
+            }
+            RenderMsg::ScrollDown {
+                lines,
+                top_boundary,
+                bottom_boundary,
+            } => {
+                // content moves up, new lines show up at the bottom
+                let (top, bottom) = boundaries(top_boundary, bottom_boundary);
+                let n = lines.len().min(bottom - top);
+                if n == 0 {
+                    return Ok(());
+                }
+                buf += &set_scroll_region(top, bottom);
+                buf += &format!("{}{};1H{}{}M", ESC, top + 1, ESC, n);
+                buf += &format!("{}{};1H", ESC, bottom - n + 1);
+                buf += &self.join_lines(&lines[lines.len() - n..]);
+            }
+            RenderMsg::ScrollUp {
+                lines,
+                top_boundary,
+                bottom_boundary,
+            } => {
+                // content moves down, new lines show up at the top
+                let (top, bottom) = boundaries(top_boundary, bottom_boundary);
+                let n = lines.len().min(bottom - top);
+                if n == 0 {
+                    return Ok(());
+                }
+                buf += &set_scroll_region(top, bottom);
+                buf += &format!("{}{};1H{}{}L", ESC, top + 1, ESC, n);
+                buf += &self.join_lines(&lines[..n]);
+            }
         }
+
+        // reset the scroll region and go back where regular frames expect
+        // the cursor to be: the last line of the frame
+        buf += ESC;
+        buf += "r";
+        buf += &format!("{}{};1H", ESC, self.last_frame.len().max(1));
+
+        write!(self.backend, "{}", buf)?;
+        self.backend.flush()?;
         Ok(())
     }
 
+    fn in_scroll_area(&self, line: usize) -> bool {
+        match self.scroll_area {
+            Some((top, bottom)) => line >= top && line < bottom,
+            None => false,
+        }
+    }
+
+    fn join_lines(&self, lines: &[String]) -> String {
+        lines
+            .iter()
+            .map(|line| format!("{}2K{}", ESC, self.fit(line)))
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+
+    // Truncate a line to the terminal width, wrapped lines would make the
+    // cursor movements between frames go out of sync.
+    fn fit(&self, line: &str) -> String {
+        if self.width == 0 {
+            return line.to_owned();
+        }
+        truncate(line, self.width as usize)
+    }
+
     pub fn hide_cursor(&mut self) -> Result<()> {
-        write!(self.stdout, "{}", termion::cursor::Hide)?;
-        self.stdout.flush().unwrap();
-        Ok(())
+        write!(self.backend, "{}", termion::cursor::Hide)?;
+        self.backend.flush()
     }
 
     pub fn show_cursor(&mut self) -> Result<()> {
-        write!(self.stdout, "{}", termion::cursor::Show)?;
-        self.stdout.flush().unwrap();
-        Ok(())
+        write!(self.backend, "{}", termion::cursor::Show)?;
+        self.backend.flush()
     }
 
-    pub fn restore_terminal(&mut self) -> Result<()> {
-        write!(self.stdout, "{}", termion::cursor::Restore)?;
-        self.stdout.flush().unwrap();
-        Ok(())
+    // Enable mouse click, release, wheel and drag reports using the SGR
+    // extended encoding, which also reports the held modifier keys.
+    pub fn enable_mouse(&mut self) -> Result<()> {
+        write!(
+            self.backend,
+            "{}",
+            ESC.to_owned() + "?1000h" + ESC + "?1002h" + ESC + "?1006h"
+        )?;
+        self.backend.flush()
+    }
+
+    pub fn disable_mouse(&mut self) -> Result<()> {
+        write!(
+            self.backend,
+            "{}",
+            ESC.to_owned() + "?1006l" + ESC + "?1002l" + ESC + "?1000l"
+        )?;
+        self.backend.flush()
+    }
+
+    // Have the terminal wrap pasted text in ESC[200~ and ESC[201~, so it's
+    // delivered as a single paste event.
+    pub fn enable_bracketed_paste(&mut self) -> Result<()> {
+        write!(self.backend, "{}?2004h", ESC)?;
+        self.backend.flush()
+    }
+
+    pub fn disable_bracketed_paste(&mut self) -> Result<()> {
+        write!(self.backend, "{}?2004l", ESC)?;
+        self.backend.flush()
+    }
+
+    // Have the terminal report when its window gains (ESC[I) or loses (ESC[O)
+    // focus.
+    pub fn enable_focus_reporting(&mut self) -> Result<()> {
+        write!(self.backend, "{}?1004h", ESC)?;
+        self.backend.flush()
     }
 
-    // Move the cursor up a given number of lines and place it at the beginning of
-    // the line
-    fn cursor_prev_line(&mut self, n: i32) -> Result<()> {
-        write!(self.stdout, "{}{}F", ESC.to_owned(), n)?;
-        //self.stdout.flush().unwrap();
+    pub fn disable_focus_reporting(&mut self) -> Result<()> {
+        write!(self.backend, "{}?1004l", ESC)?;
+        self.backend.flush()
+    }
+
+    // Ask for the kitty keyboard protocol, so keys like Ctrl+Enter or Escape
+    // can be told apart. Terminals without it ignore the request.
+    pub fn enable_kitty_keyboard(&mut self) -> Result<()> {
+        write!(self.backend, "{}>1u", ESC)?;
+        self.backend.flush()
+    }
+
+    pub fn disable_kitty_keyboard(&mut self) -> Result<()> {
+        write!(self.backend, "{}<u", ESC)?;
+        self.backend.flush()
+    }
+
+    pub fn enable_raw_mode(&mut self) -> Result<()> {
+        self.backend.enable_raw_mode()
+    }
+
+    pub fn disable_raw_mode(&mut self) -> Result<()> {
+        self.backend.disable_raw_mode()
+    }
+
+    // Switch to the altscreen and clear it.
+    pub fn enter_fullscreen(&mut self) -> Result<()> {
+        write!(self.backend, "{}", ESC.to_owned() + "?1049h" + ESC + "H")?;
+        self.backend.flush()
+    }
+
+    // Return to the former terminal view.
+    pub fn exit_fullscreen(&mut self) -> Result<()> {
+        write!(self.backend, "{}", ESC.to_owned() + "?1049l")?;
+        self.backend.flush()?;
+        // frames were rendered on the altscreen, nothing to diff against
+        self.last_frame.clear();
         Ok(())
     }
 
-    fn clear_line(&mut self) -> Result<()> {
-        write!(self.stdout, "{}", ESC.to_owned() + "2K")?;
-        //self.stdout.flush().unwrap();
+    // Erase the last frame, the cursor is left where the frame started.
+    pub(crate) fn erase(&mut self) -> Result<()> {
+        if self.last_frame.is_empty() {
+            return Ok(());
+        }
+        if self.last_frame.len() > 1 {
+            write!(self.backend, "{}{}F", ESC, self.last_frame.len() - 1)?;
+        }
+        write!(self.backend, "\r{}J", ESC)?;
+        self.backend.flush()?;
+        self.last_frame.clear();
         Ok(())
     }
 
-    fn clear_lines(&mut self, n: i32) -> Result<()> {
-        for _ in 0..n {
-            self.cursor_prev_line(1)?;
-            self.clear_line()?;
+    // Hand the terminal over to another process, the view is left behind
+    // like on exit.
+    pub(crate) fn release(&mut self, fullscreen: bool, mouse: bool) -> Result<()> {
+        if mouse {
+            self.disable_mouse()?;
+        }
+        self.disable_bracketed_paste()?;
+        self.disable_focus_reporting()?;
+        self.disable_kitty_keyboard()?;
+        if fullscreen {
+            self.exit_fullscreen()?;
+        }
+        self.show_cursor()?;
+        self.restore_terminal()?;
+        self.backend.pause_input();
+        self.disable_raw_mode()
+    }
+
+    // Take the terminal back after release, the next frame is rendered from
+    // scratch below the cursor.
+    pub(crate) fn acquire(&mut self, fullscreen: bool, mouse: bool) -> Result<()> {
+        self.enable_raw_mode()?;
+        self.backend.resume_input();
+        if fullscreen {
+            self.enter_fullscreen()?;
         }
+        self.hide_cursor()?;
+        self.enable_bracketed_paste()?;
+        self.enable_focus_reporting()?;
+        self.enable_kitty_keyboard()?;
+        if mouse {
+            self.enable_mouse()?;
+        }
+        self.last_frame.clear();
+        self.repaint = true;
         Ok(())
     }
+
+    pub fn restore_terminal(&mut self) -> Result<()> {
+        if !self.last_frame.is_empty() {
+            // leave the cursor below the last frame
+            write!(self.backend, "\r\n")?;
+        }
+        self.backend.flush()
+    }
 }
 
-// invert inverts the foreground and background colors of a given string
-pub fn invert(s: String) -> String {
-    ESC.to_owned() + "7m" + &s + ESC + "0m"
+// Bring the terminal back to a usable state after a panic, without going
+// through a renderer: it may be the one that panicked. Does nothing if the
+// backend is in use.
+pub(crate) fn restore_after_panic(backend: &SharedBackend, fullscreen: bool, mouse: bool) {
+    let mut backend = match backend.try_lock() {
+        Some(backend) => backend,
+        None => return,
+    };
+
+    let mut buf = String::new();
+    if mouse {
+        buf += &(ESC.to_owned() + "?1006l" + ESC + "?1002l" + ESC + "?1000l");
+    }
+    buf += &(ESC.to_owned() + "?2004l" + ESC + "?1004l" + ESC + "<u");
+    if fullscreen {
+        buf += &(ESC.to_owned() + "?1049l");
+    } else {
+        buf += "\r\n";
+    }
+    buf += termion::cursor::Show.as_ref();
+
+    write!(backend, "{}", buf).ok();
+    backend.flush().ok();
+    backend.disable_raw_mode().ok();
 }
 
-// fullscreen switches to the altscreen and clears the terminal. The former
-// view can be restored with exit_fullscreen().
-pub fn fullscreen() {
-    print!("{}", ESC.to_owned() + "?1049h" + ESC + "H");
+fn boundaries(top_boundary: isize, bottom_boundary: isize) -> (usize, usize) {
+    let top = top_boundary.max(0) as usize;
+    let bottom = bottom_boundary.max(0) as usize;
+    (top, bottom.max(top))
 }
 
-// exit_fullscreen exits the altscreen and returns the former terminal view
-pub fn exit_fullscreen() {
-    print!("{}", ESC.to_owned() + "?1049l");
+// set_scroll_region restricts scrolling to the rows between top and bottom
+fn set_scroll_region(top: usize, bottom: usize) -> String {
+    format!("{}{};{}r", ESC, top + 1, bottom)
+}
+
+// invert inverts the foreground and background colors of a given string
+pub fn invert(s: String) -> String {
+    ESC.to_owned() + "7m" + &s + ESC + "0m"
 }
 
 // HIGH-PERFORMANCE RENDERING STUFF
@@ -124,8 +422,8 @@ pub fn sync_scroll_area(
     top_boundary: isize,
     bottom_boundary: isize,
 ) -> Cmd<RenderMsg> {
-    Box::new(move || RenderMsg::SyncScrollArea {
-        lines: lines.clone(),
+    commands::message(RenderMsg::SyncScrollArea {
+        lines,
         top_boundary,
         bottom_boundary,
     })
@@ -136,8 +434,8 @@ pub fn scroll_down(
     top_boundary: isize,
     bottom_boundary: isize,
 ) -> Cmd<RenderMsg> {
-    Box::new(move || RenderMsg::ScrollDown {
-        lines: new_lines.clone(),
+    commands::message(RenderMsg::ScrollDown {
+        lines: new_lines,
         top_boundary,
         bottom_boundary,
     })
@@ -148,9 +446,58 @@ pub fn scroll_up(
     top_boundary: isize,
     bottom_boundary: isize,
 ) -> Cmd<RenderMsg> {
-    Box::new(move || RenderMsg::ScrollUp {
-        lines: new_lines.clone(),
+    commands::message(RenderMsg::ScrollUp {
+        lines: new_lines,
         top_boundary,
         bottom_boundary,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{SharedBuffer, StreamBackend};
+
+    // A renderer writing to a buffer, and what was written since last asked.
+    fn renderer() -> (Renderer, impl FnMut() -> String) {
+        let (backend, buffer): (_, SharedBuffer) = StreamBackend::in_memory(20, 10);
+        let mut seen = 0;
+        let written = move || {
+            let contents = buffer.contents();
+            let new = contents[seen..].to_string();
+            seen = contents.len();
+            new
+        };
+        (Renderer::new(Box::new(backend)), written)
+    }
+
+    #[test]
+    fn rewrites_only_changed_lines() {
+        let (mut renderer, mut written) = renderer();
+        renderer.render("one\ntwo\nthree").unwrap();
+        assert_eq!(written(), "\r\x1B[2Kone\r\n\x1B[2Ktwo\r\n\x1B[2Kthree");
+
+        renderer.render("one\n2\nthree").unwrap();
+        assert_eq!(written(), "\x1B[2F\r\n\x1B[2K2\r\n");
+    }
+
+    #[test]
+    fn clears_lines_left_by_a_longer_frame() {
+        let (mut renderer, mut written) = renderer();
+        renderer.render("one\ntwo\nthree").unwrap();
+        written();
+
+        renderer.render("one").unwrap();
+        assert_eq!(written(), "\x1B[2F\r\n\x1B[J\x1B[1A");
+    }
+
+    #[test]
+    fn writes_nothing_for_an_unchanged_frame() {
+        let (mut renderer, mut written) = renderer();
+        renderer.render("one\ntwo").unwrap();
+        written();
+
+        renderer.render("one\ntwo").unwrap();
+        assert_eq!(written(), "");
+    }
+}
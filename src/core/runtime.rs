@@ -1,20 +1,78 @@
-use std::{cell::RefCell, io::Result, rc::Rc, sync::mpsc::Sender, thread};
+use std::{
+    any::Any,
+    cell::RefCell,
+    io::{Error, ErrorKind, Result},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use addy::Signal::SIGWINCH;
 
 use crate::{
+    backend::{Backend, TermionBackend},
+    color,
     input::{receive_inputs, InputEvent},
-    renderer::{exit_fullscreen, fullscreen, Renderer},
-    store::{Middleware, Subscription},
+    renderer::{restore_after_panic, RenderMsg, Renderer},
+    store::Middleware,
+    subscriptions::Subscriptions,
     Channel,
 };
 
-use super::{heartbeat::Heartbeat, render_channel::RenderChannel, store::Store};
+use super::{
+    executor::{Context, Executor},
+    guard::{PanicGuard, SignalGuard},
+    heartbeat::Heartbeat,
+    render_channel::{NextFrame, RenderChannel},
+    store::Store,
+    subscriptions::Running,
+};
+
+pub use super::commands::{BatchCmd, Cmd};
+use super::commands::{ExitValue, Handoff};
 
 pub type Initialize<Model, Message> = fn() -> (Model, Option<Cmd<Message>>);
 
-pub type Cmd<Message> = Box<dyn Fn() -> Message + Send + Sync>;
-pub type BatchCmd<Message> = Vec<Cmd<Message>>;
+const DEFAULT_FPS: u32 = 60;
+const DEFAULT_WORKERS: usize = 8;
+
+// tells the runtimes running at the same time apart, e.g. in signal handlers
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Exit is what a program leaves behind once it stops: its final model and
+/// the value given to `commands::quit_with`, if any.
+pub struct Exit<Model> {
+    pub model: Model,
+    value: Option<ExitValue>,
+}
+
+impl<Model> Exit<Model> {
+    /// The exit value, if one of type `T` was set.
+    pub fn value<T: Any>(&self) -> Option<&T> {
+        self.value.as_ref()?.downcast_ref()
+    }
+
+    pub fn into_value<T: Any>(self) -> Option<T> {
+        self.value?.downcast().ok().map(|value| *value)
+    }
+}
+
+/// Cleanup decides what an inline program leaves in the terminal when it
+/// exits. A fullscreen program never leaves its frames behind, only a summary.
+pub enum Cleanup<Model> {
+    /// Leave the final frame, this is the default.
+    Keep,
+    /// Erase the program, as if it never ran.
+    Erase,
+    /// Replace the final frame with a summary of the final model, e.g. the
+    /// answer to a prompt.
+    Summary(fn(&Model) -> String),
+}
 
 pub struct Runtime<Model: Sync, Message, Input, View, Reducer> {
     heartbeat: Heartbeat,
@@ -22,6 +80,14 @@ pub struct Runtime<Model: Sync, Message, Input, View, Reducer> {
     ignition: Option<Cmd<Message>>, // first command to execute
     input: Input,
     view: View,
+    backend: Option<Box<dyn Backend>>,
+    fullscreen: bool,
+    mouse: bool,
+    fps: u32,
+    workers: usize,
+    render_messages: Option<fn(&Message) -> Option<RenderMsg>>,
+    subscriptions: Option<Subscriptions<Model, Message>>,
+    cleanup: Cleanup<Model>,
 }
 
 impl<Model, Message, Input, View, Reducer, Command> Runtime<Model, Message, Input, View, Reducer>
@@ -31,7 +97,7 @@ where
     Input: Fn(InputEvent) -> Option<Message> + Send + Sync + Copy + 'static,
     View: 'static + Fn(&Model) -> String + Send,
     Reducer: Fn(Model, Message) -> (Model, Vec<Command>),
-    Command: Fn() -> Message + Send + 'static,
+    Command: Into<Cmd<Message>>,
 {
     pub fn new(
         reducer: Reducer,
@@ -48,11 +114,66 @@ where
             ignition: cmd,
             input,
             view,
+            backend: None,
+            fullscreen: false,
+            mouse: false,
+            fps: DEFAULT_FPS,
+            workers: DEFAULT_WORKERS,
+            render_messages: None,
+            subscriptions: None,
+            cleanup: Cleanup::Keep,
         }
     }
 
-    pub fn with_fullscreen(self) -> Self {
-        fullscreen();
+    pub fn with_fullscreen(mut self) -> Self {
+        self.fullscreen = true;
+        self
+    }
+
+    /// What to leave behind on exit, see `Cleanup`. Without fullscreen the
+    /// program renders inline, below the cursor.
+    pub fn with_cleanup(mut self, cleanup: Cleanup<Model>) -> Self {
+        self.cleanup = cleanup;
+        self
+    }
+
+    /// Render to and read input from the given backend instead of the
+    /// terminal attached to stdout and stdin
+    pub fn with_backend<B: Backend + 'static>(mut self, backend: B) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Enable mouse reporting, mouse events will be delivered to the input
+    /// function as `InputEvent::Mouse`
+    pub fn with_mouse(mut self) -> Self {
+        self.mouse = true;
+        self
+    }
+
+    /// Limit how many frames are rendered per second, defaults to 60.
+    /// Messages are still reduced as soon as they arrive, but the view is
+    /// rendered at most once per frame and only if a message was reduced
+    /// since the last one.
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = fps.max(1);
+        self
+    }
+
+    /// Number of threads commands are executed on, defaults to 8. Commands
+    /// run concurrently with the main loop and each other, when all workers
+    /// are busy the next commands wait for one to be free.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Messages for which `extract` returns a `RenderMsg` are handed to the
+    /// renderer instead of the reducer. This is how components using
+    /// high-performance rendering, like the viewport, get to scroll their
+    /// area of the screen
+    pub fn with_render_messages(mut self, extract: fn(&Message) -> Option<RenderMsg>) -> Self {
+        self.render_messages = Some(extract);
         self
     }
 
@@ -62,125 +183,274 @@ where
         self
     }
 
-    /// Will execute every time
-    pub fn with_subscription(mut self, subscription: Subscription<Model, Message>) -> Self {
-        self.store.subscribe(subscription);
+    /// Compute the subscriptions of the program from its model, after every
+    /// update. Subscriptions are started, kept or stopped by key, see `Sub`.
+    pub fn with_subscriptions(mut self, subscriptions: Subscriptions<Model, Message>) -> Self {
+        self.subscriptions = Some(subscriptions);
         self
     }
 
-    pub fn run(mut self) -> Result<()> {
-        let render_channel = RenderChannel::new();
-        let render_receiver = render_channel.receiver.clone();
+    /// Run the program until a quit command is executed, returns its final
+    /// model and exit value.
+    pub fn run(mut self) -> Result<Exit<Model>> {
+        let (render_channel, render_receiver) = RenderChannel::new();
 
-        let mut channel = Channel::new();
+        let mut channel = Channel::new(render_channel.clone());
         let input_sender = channel.sender();
 
-        // change terminal mode
-        let renderer = Rc::new(RefCell::new(Renderer::new()));
-        renderer.borrow_mut().hide_cursor()?;
+        let mut backend = match self.backend.take() {
+            Some(backend) => backend,
+            None => Box::new(TermionBackend::new()?),
+        };
+        let reader = backend.reader()?;
+        color::detect_profile_for(backend.is_terminal());
 
-        // execute fist command
-        if let Some(cmd) = self.ignition {
-            execute(&mut channel, cmd);
-        }
+        let mut renderer = Renderer::new(backend);
 
-        let messages = Rc::new(RefCell::new(Vec::new()));
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
 
-        // Render initial view
-        let first_frame = (self.view)(&self.store.model());
-        renderer.borrow_mut().render(&first_frame)?;
-
-        // input thread
-        thread::spawn(move || {
-            receive_inputs(self.input, input_sender);
+        // restore the terminal before the message is printed if the reducer or
+        // the view panics
+        let panic_backend = renderer.backend();
+        let (fullscreen, mouse) = (self.fullscreen, self.mouse);
+        let _panic_guard = PanicGuard::new(id, move || {
+            restore_after_panic(&panic_backend, fullscreen, mouse)
         });
 
-        let (w, h, terminal_size_sender) = send_terminal_resize_message(&mut channel);
+        // SIGINT and SIGTERM stop the program like a quit command would, but
+        // run returns an error
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let _signal_guard = {
+            let interrupted = interrupted.clone();
+            let heartbeat = self.heartbeat.clone();
+            let render_channel = render_channel.clone();
+            SignalGuard::new(format!("stop_{}", id), move |_signal| {
+                interrupted.store(true, Ordering::SeqCst);
+                heartbeat.kill();
+                render_channel.next_frame();
+            })
+        };
 
-        let msg = (self.input)(InputEvent::WindowSize {
-            width: w,
-            height: h,
-        });
+        let cmd_sender = Mutex::new(channel.sender());
+        let alive = self.heartbeat.clone();
+        let heartbeat = self.heartbeat.clone();
+        let quit_channel = render_channel.clone();
+        let exit_value = Arc::new(Mutex::new(None));
+        let quit_value = exit_value.clone();
+        let (handoff_sender, handoff_receiver) = mpsc::channel();
+        let handoff_sender = Mutex::new(handoff_sender);
+        let handoff_channel = render_channel.clone();
+        let commands = Context::new(
+            move |msg| !alive.is_dead() && cmd_sender.lock().unwrap().send(msg),
+            move |value| {
+                if value.is_some() {
+                    *quit_value.lock().unwrap() = value;
+                }
+                heartbeat.kill();
+                quit_channel.next_frame();
+            },
+            // commands needing the terminal are run by the main loop
+            move |handoff| {
+                handoff_sender.lock().unwrap().send(handoff).ok();
+                handoff_channel.next_frame();
+            },
+            Some(Executor::new(self.workers)),
+        );
 
-        if let Some(msg) = msg {
-            terminal_size_sender.send(msg);
-            render_channel.next_frame();
-        }
+        let ignition = self.ignition.take();
+        let resize_handler = format!("terminal_size_change_{}", id);
+        let mut running = Running::default();
 
-        // watch terminal size changes
-        addy::mediate(SIGWINCH)
-            .register("terminal_size_change", move |_signal| {
-                let (w, h) = termion::terminal_size().expect("could not get terminal size");
-                let msg = &(self.input)(InputEvent::WindowSize {
-                    width: w,
-                    height: h,
-                });
-                if let Some(msg) = msg {
-                    terminal_size_sender.send(msg.clone());
-                    let render_channel = RenderChannel::new();
-                    render_channel.next_frame();
-                }
-            })
-            .expect("could not register input handler to terminal_size_change event")
-            .enable()
-            .expect("could not enable terminal_size_change event");
-
-        {
-            // render callback
-            let renderer = renderer.clone();
-            self.store.react(move |model| {
-                let next_frame = (self.view)(model);
-                renderer.borrow_mut().render(&next_frame);
+        // the terminal is given back even if the loop fails, its error is
+        // returned once that's done
+        let result = (|| -> Result<()> {
+            // change terminal mode
+            renderer.enable_raw_mode()?;
+            if self.fullscreen {
+                renderer.enter_fullscreen()?;
+            }
+            renderer.hide_cursor()?;
+            renderer.enable_bracketed_paste()?;
+            renderer.enable_focus_reporting()?;
+            renderer.enable_kitty_keyboard()?;
+            if self.mouse {
+                renderer.enable_mouse()?;
+            }
+
+            // execute fist command
+            if let Some(cmd) = ignition {
+                commands.execute(cmd);
+            }
+
+            let messages = Rc::new(RefCell::new(Vec::new()));
+
+            // Render initial view
+            let first_frame = (self.view)(&self.store.model());
+            renderer.render(&first_frame)?;
+
+            // input thread
+            let input = self.input;
+            thread::spawn(move || {
+                receive_inputs(reader, input, input_sender);
             });
-        }
 
-        self.store = self.store.dispatch_subscriptions(&mut channel);
-
-        // main loop, update states when MSG is received,
-        // draw when ShouldRender is received
-        for _ in render_receiver.iter() {
-            let mut borrowed = messages.borrow_mut();
-            borrowed.extend(channel.rx.try_iter());
-            for msg in borrowed.drain(..) {
-                let cmd_sender = channel.sender();
-                // dispatch reducer and execute render callback
-                self.store.dispatch(msg, cmd_sender);
+            let (w, h) = renderer.size()?;
+            if let Some(msg) = input(InputEvent::WindowSize {
+                width: w,
+                height: h,
+            }) {
+                channel.sender().send(msg);
             }
 
-            if self.heartbeat.is_dead() {
-                exit_fullscreen();
-                break;
+            // watch terminal size changes, the new size is read from the backend
+            // on the main loop. Every runtime registers its own handler.
+            let resize_channel = render_channel.clone();
+            addy::mediate(SIGWINCH)
+                .register(&resize_handler, move |_signal| {
+                    resize_channel.resize();
+                })
+                .expect("could not register input handler to terminal_size_change event")
+                .enable()
+                .expect("could not enable terminal_size_change event");
+
+            if let Some(subscriptions) = self.subscriptions {
+                running.update(subscriptions(&self.store.model()), &commands);
             }
+
+            let frame_duration = Duration::from_secs(1) / self.fps;
+            let mut last_render = Instant::now();
+            let mut dirty = false; // a message was reduced since the last frame
+
+            // main loop, update states as soon as a MSG is received and draw
+            // at most once per frame
+            loop {
+                let next = if dirty {
+                    let wait = frame_duration
+                        .checked_sub(last_render.elapsed())
+                        .unwrap_or_default();
+                    match render_receiver.recv_timeout(wait) {
+                        Ok(next) => Some(next),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                } else {
+                    match render_receiver.recv() {
+                        Ok(next) => Some(next),
+                        Err(_) => break,
+                    }
+                };
+
+                if let Some(NextFrame::Resize) = next {
+                    let (width, height) = renderer.resize()?;
+                    dirty = true;
+                    if let Some(msg) = input(InputEvent::WindowSize { width, height }) {
+                        channel.sender().send(msg);
+                    }
+                }
+
+                let mut reduced = false;
+                let mut borrowed = messages.borrow_mut();
+                borrowed.extend(channel.rx.try_iter());
+                for msg in borrowed.drain(..) {
+                    if let Some(render_msg) = self.render_messages.and_then(|extract| extract(&msg))
+                    {
+                        renderer.handle(render_msg)?;
+                        continue;
+                    }
+
+                    for cmd in self.store.dispatch(msg) {
+                        commands.execute(cmd);
+                    }
+                    reduced = true;
+                }
+
+                for handoff in handoff_receiver.try_iter() {
+                    renderer.release(self.fullscreen, self.mouse)?;
+                    let msg = match handoff {
+                        Handoff::Suspend => {
+                            suspend();
+                            None
+                        }
+                        Handoff::Exec(mut command, done) => Some(done(command.status())),
+                    };
+                    renderer.acquire(self.fullscreen, self.mouse)?;
+
+                    // the terminal may have been resized in the meantime
+                    render_channel.resize();
+                    if let Some(msg) = msg {
+                        channel.sender().send(msg);
+                    }
+                    dirty = true;
+                }
+
+                if reduced {
+                    if let Some(subscriptions) = self.subscriptions {
+                        running.update(subscriptions(&self.store.model()), &commands);
+                    }
+                    dirty = true;
+                }
+
+                let is_dead = self.heartbeat.is_dead();
+
+                // render the final frame right away
+                if dirty && (is_dead || last_render.elapsed() >= frame_duration) {
+                    let next_frame = (self.view)(&self.store.model());
+                    renderer.render(&next_frame)?;
+                    last_render = Instant::now();
+                    dirty = false;
+                }
+
+                if is_dead {
+                    break;
+                }
+            }
+            Ok(())
+        })();
+
+        running.stop();
+        addy::mediate(SIGWINCH).remove(&resize_handler).ok();
+        let mut restored = vec![];
+        if self.mouse {
+            restored.push(renderer.disable_mouse());
         }
-        renderer.borrow_mut().show_cursor()?;
-        renderer.borrow_mut().restore_terminal()?;
-        Ok(())
-    }
-}
+        restored.push(renderer.disable_bracketed_paste());
+        restored.push(renderer.disable_focus_reporting());
+        restored.push(renderer.disable_kitty_keyboard());
+        if self.fullscreen {
+            restored.push(renderer.exit_fullscreen());
+        }
+        match self.cleanup {
+            Cleanup::Keep => {}
+            Cleanup::Erase => restored.push(renderer.erase()),
+            Cleanup::Summary(summary) => {
+                restored.push(renderer.erase());
+                restored.push(renderer.render(&summary(&self.store.model())));
+            }
+        }
+        restored.push(renderer.show_cursor());
+        restored.push(renderer.restore_terminal());
+        restored.push(renderer.disable_raw_mode());
+        result?;
+        restored.into_iter().collect::<Result<()>>()?;
 
-fn send_terminal_resize_message<Message: Send + Sync + 'static>(
-    channel: &mut Channel<Message>,
-) -> (u16, u16, Sender<Message>) {
-    let (w, h) = termion::terminal_size().expect("could not get terminal size");
-    let (terminal_size_sender, terminal_size_receiver) = std::sync::mpsc::channel();
-    let mut cross_channel_sender = channel.sender();
-    thread::spawn(move || {
-        for msg in terminal_size_receiver.iter() {
-            cross_channel_sender.send(msg);
+        if interrupted.load(Ordering::SeqCst) {
+            return Err(Error::new(
+                ErrorKind::Interrupted,
+                "interrupted by a signal",
+            ));
         }
-    });
-    (w, h, terminal_size_sender)
+
+        let value = exit_value.lock().unwrap().take();
+        Ok(Exit {
+            model: self.store.model(),
+            value,
+        })
+    }
 }
 
-fn execute<Message: Send + Sync + 'static>(
-    channel: &mut Channel<Message>,
-    cmd: Box<dyn Fn() -> Message + Send + Sync>,
-) {
-    let mut cmd_sender = channel.sender();
-    thread::spawn(move || {
-        let msg = cmd();
-        cmd_sender.send(msg);
-    })
-    .join()
-    .expect("failed to join cmd thread");
+// Stop the process until it gets SIGCONT, like Ctrl-Z does in a shell.
+fn suspend() {
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
 }
@@ -1,13 +1,10 @@
-use std::{thread};
+use generational_arena::Arena;
 
-use generational_arena::{Arena};
-
-use crate::{BatchCmd, Channel, ChannelSender};
+use crate::{BatchCmd, Cmd};
 
 pub type Middleware<Model, Message, Reducer> =
     fn(&mut Store<Model, Message, Reducer>, Message) -> Option<Message>;
 pub type Reaction<Model> = dyn Fn(&Model);
-pub type Subscription<Model, Message> = fn(&Model) -> Message;
 pub type Reducer<Model, Message> = fn(Model, Message) -> (Model, BatchCmd<Message>);
 
 pub struct Store<Model: Sync, Message, Reducer> {
@@ -15,14 +12,13 @@ pub struct Store<Model: Sync, Message, Reducer> {
     model: Model,
     middleware: Vec<Middleware<Model, Message, Reducer>>,
     reactions: Arena<Box<Reaction<Model>>>,
-    subscriptions: Vec<Subscription<Model, Message>>,
 }
 
 impl<Model: Clone + Send + Sync + 'static, Message: Send + 'static, Reducer, Command>
     Store<Model, Message, Reducer>
 where
     Reducer: Fn(Model, Message) -> (Model, Vec<Command>),
-    Command: Fn() -> Message + Send + 'static,
+    Command: Into<Cmd<Message>>,
 {
     pub fn new(reducer: Reducer, initial: Model) -> Self {
         Self {
@@ -30,7 +26,6 @@ where
             model: initial,
             middleware: vec![],
             reactions: Arena::new(),
-            subscriptions: Vec::new(),
         }
     }
 
@@ -38,11 +33,13 @@ where
         self.model.clone()
     }
 
-    pub fn dispatch(&mut self, message: Message, cmd_sender: ChannelSender<Message>) {
+    /// Run the message through the middleware and the reducer. The commands
+    /// returned by the reducer are not executed, that is up to the caller.
+    pub fn dispatch(&mut self, message: Message) -> BatchCmd<Message> {
         if self.middleware.is_empty() {
-            self.dispatch_reducer(message, cmd_sender);
+            self.dispatch_reducer(message)
         } else {
-            self.dispatch_middleware(0, message, cmd_sender);
+            self.dispatch_middleware(0, message)
         }
     }
 
@@ -50,56 +47,31 @@ where
         self.reactions.insert(Box::new(callback));
     }
 
-    pub fn subscribe(&mut self, callback: Subscription<Model, Message>) {
-        self.subscriptions.push(callback);
-    }
-
     pub fn middleware(&mut self, callback: Middleware<Model, Message, Reducer>) {
         self.middleware.push(callback);
     }
 
-    pub fn dispatch_subscriptions(self, channel: &mut Channel<Message>) -> Self {
-        for subscription in self.subscriptions.clone() {
-            let model = self.model(); // needs clone :(
-            let mut subscription_sender = channel.sender();
-            thread::spawn(move || loop {
-                let message = subscription(&model);
-                subscription_sender.send(message);
-            });
-        }
-        self
-    }
-
-    fn dispatch_middleware(
-        &mut self,
-        index: usize,
-        message: Message,
-        cmd_sender: ChannelSender<Message>,
-    ) {
+    fn dispatch_middleware(&mut self, index: usize, message: Message) -> BatchCmd<Message> {
         if index == self.middleware.len() {
-            self.dispatch_reducer(message, cmd_sender);
-            return;
+            return self.dispatch_reducer(message);
         }
 
         let next = self.middleware[index](self, message);
 
         if next.is_none() {
-            return;
+            return vec![];
         }
 
-        self.dispatch_middleware(index + 1, next.unwrap(), cmd_sender);
+        self.dispatch_middleware(index + 1, next.unwrap())
     }
 
-    fn dispatch_reducer(&mut self, message: Message, mut cmd_sender: ChannelSender<Message>) {
-        let (model, mut cmds) = (&self.reducer)(self.model(), message);
+    fn dispatch_reducer(&mut self, message: Message) -> BatchCmd<Message> {
+        let (model, cmds) = (&self.reducer)(self.model(), message);
         self.model = model;
 
-        for cmd in cmds.drain(..) {
-            let msg = cmd();
-            cmd_sender.send(msg);
-        }
-
         self.dispatch_reactions(&self.model);
+
+        cmds.into_iter().map(Into::into).collect()
     }
 
     fn dispatch_reactions(&self, model: &Model) {
@@ -0,0 +1,531 @@
+use super::{
+    ansi,
+    border::Border,
+    color::{convert_hex_rgb, profile, Rgb},
+};
+
+const RESET: &str = "\x1B[0m";
+
+/// Align places lines in the width of a style.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+// A size for each side of a block, like CSS padding and margin.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+struct Sides {
+    top: usize,
+    right: usize,
+    bottom: usize,
+    left: usize,
+}
+
+/// VerticalAlign places lines in the height of a block.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+}
+
+// The sides of a block drawn with the border.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+struct Edges {
+    top: bool,
+    right: bool,
+    bottom: bool,
+    left: bool,
+}
+
+/// Style describes how text is rendered: colors and attributes, then padding
+/// inside the block, a border and margin around it. Styles are built with the with_*
+/// methods and applied with render:
+///
+/// ```
+/// use moonlight::style::{Align, Style};
+///
+/// let title = Style::new()
+///     .with_foreground("#FAFAFA")
+///     .with_background("#7D56F4")
+///     .with_bold()
+///     .with_padding(0, 1, 0, 1)
+///     .with_width(20)
+///     .with_align(Align::Center);
+/// let view = title.render("Moonlight");
+/// ```
+///
+/// Colors are downsampled to the terminal's color profile, see
+/// `color::profile`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    foreground: Option<Rgb>,
+    background: Option<Rgb>,
+    bold: bool,
+    faint: bool,
+    italic: bool,
+    underline: bool,
+    reverse: bool,
+    strikethrough: bool,
+    padding: Sides,
+    margin: Sides,
+    width: Option<usize>,
+    height: Option<usize>,
+    align: Align,
+    border: Option<Border>,
+    border_sides: Edges,
+    border_foreground: Option<Rgb>,
+    border_background: Option<Rgb>,
+    border_title: String,
+    border_title_align: Align,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the text color, a CSS color like "#FF5F87". Invalid colors are
+    /// ignored.
+    pub fn with_foreground(mut self, color: &str) -> Self {
+        self.foreground = convert_hex_rgb(color).ok();
+        self
+    }
+
+    /// Set the color behind the text and the padding. Invalid colors are
+    /// ignored.
+    pub fn with_background(mut self, color: &str) -> Self {
+        self.background = convert_hex_rgb(color).ok();
+        self
+    }
+
+    pub fn with_bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn with_faint(mut self) -> Self {
+        self.faint = true;
+        self
+    }
+
+    pub fn with_italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn with_underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Swap the foreground and background colors.
+    pub fn with_reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    pub fn with_strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    /// Add space inside the block, it has the background color.
+    pub fn with_padding(mut self, top: usize, right: usize, bottom: usize, left: usize) -> Self {
+        self.padding = Sides {
+            top,
+            right,
+            bottom,
+            left,
+        };
+        self
+    }
+
+    /// Add space around the block, it's never styled.
+    pub fn with_margin(mut self, top: usize, right: usize, bottom: usize, left: usize) -> Self {
+        self.margin = Sides {
+            top,
+            right,
+            bottom,
+            left,
+        };
+        self
+    }
+
+    /// Set the width of the block, padding included and margin excluded.
+    /// Longer lines are wrapped at the spaces, shorter ones are aligned.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Set the minimum height of the block, padding included and margin
+    /// excluded. Empty lines are added below the text.
+    pub fn with_height(mut self, height: usize) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Draw a border around the block, between the padding and the margin.
+    /// All the sides are drawn unless some were picked with
+    /// with_border_sides.
+    pub fn with_border(mut self, border: Border) -> Self {
+        self.border = Some(border);
+        if self.border_sides == Edges::default() {
+            self.border_sides = Edges {
+                top: true,
+                right: true,
+                bottom: true,
+                left: true,
+            };
+        }
+        self
+    }
+
+    /// Pick the sides of the border to draw, the normal border is used if
+    /// none was set.
+    pub fn with_border_sides(mut self, top: bool, right: bool, bottom: bool, left: bool) -> Self {
+        self.border_sides = Edges {
+            top,
+            right,
+            bottom,
+            left,
+        };
+        if self.border.is_none() {
+            self.border = Some(Border::normal());
+        }
+        self
+    }
+
+    /// Set the color of the border. Invalid colors are ignored.
+    pub fn with_border_foreground(mut self, color: &str) -> Self {
+        self.border_foreground = convert_hex_rgb(color).ok();
+        self
+    }
+
+    /// Set the color behind the border. Invalid colors are ignored.
+    pub fn with_border_background(mut self, color: &str) -> Self {
+        self.border_background = convert_hex_rgb(color).ok();
+        self
+    }
+
+    /// Embed a title in the top edge of the border, placed by align. The
+    /// title is written as given, it may be styled, and cut if it doesn't
+    /// fit.
+    pub fn with_border_title(mut self, title: &str, align: Align) -> Self {
+        self.border_title = title.to_string();
+        self.border_title_align = align;
+        self
+    }
+
+    /// render applies the style to the text. The lines of the result all
+    /// have the same width, so styled blocks can be placed next to each
+    /// other.
+    pub fn render(&self, text: &str) -> String {
+        let text = text.replace('\t', "    ");
+        let (padding, margin) = (self.padding, self.margin);
+
+        let mut lines = ansi::lines(&text);
+        let inner = self
+            .width
+            .map(|width| width.saturating_sub(padding.left + padding.right));
+        if let Some(inner) = inner {
+            lines = lines
+                .iter()
+                .flat_map(|line| ansi::word_wrap(line, inner.max(1)))
+                // a wide character may not fit even on a line of its own
+                .map(|line| ansi::truncate(&line, inner))
+                .collect();
+        }
+        let inner =
+            inner.unwrap_or_else(|| lines.iter().map(|l| ansi::width(l)).max().unwrap_or(0));
+
+        let (open, background) = (self.open(), self.background_open());
+        let blank = |width: usize| paint(&" ".repeat(width), &background);
+
+        let mut block: Vec<String> = vec![];
+        let full = padding.left + inner + padding.right;
+        for _ in 0..padding.top {
+            block.push(blank(full));
+        }
+        for line in &lines {
+            let space = inner.saturating_sub(ansi::width(line));
+            let (before, after) = match self.align {
+                Align::Left => (0, space),
+                Align::Center => (space / 2, space - space / 2),
+                Align::Right => (space, 0),
+            };
+            // resets inside the text would end the style early
+            let line = line.replace(RESET, &(RESET.to_string() + &open));
+            block.push(
+                blank(padding.left + before) + &paint(&line, &open) + &blank(after + padding.right),
+            );
+        }
+        for _ in 0..padding.bottom {
+            block.push(blank(full));
+        }
+        while block.len() < self.height.unwrap_or(0) {
+            block.push(blank(full));
+        }
+
+        if let Some(border) = &self.border {
+            block = self.draw_border(border, block, full);
+        }
+        let full = block.first().map_or(full, |line| ansi::width(line));
+
+        let width = margin.left + full + margin.right;
+        let mut out = vec![" ".repeat(width); margin.top];
+        for line in block {
+            out.push(" ".repeat(margin.left) + &line + &" ".repeat(margin.right));
+        }
+        out.extend(vec![" ".repeat(width); margin.bottom]);
+        out.join("\n")
+    }
+
+    // Surround the lines of the block, all of the given width, with the
+    // border sides.
+    fn draw_border(&self, border: &Border, block: Vec<String>, width: usize) -> Vec<String> {
+        let sides = self.border_sides;
+        let params: Vec<String> = self
+            .border_foreground
+            .and_then(|c| c.sgr(profile(), false))
+            .into_iter()
+            .chain(self.border_background.and_then(|c| c.sgr(profile(), true)))
+            .collect();
+        let open = sgr(&params);
+        let draw = |s: &str, width: usize| paint(&fill(s, width), &open);
+
+        let left = if sides.left {
+            ansi::width(&border.left)
+        } else {
+            0
+        };
+        let right = if sides.right {
+            ansi::width(&border.right)
+        } else {
+            0
+        };
+        let edge = |corner_left: &str, edge: &str, corner_right: &str, title: &str| {
+            let title = ansi::truncate(title, width.saturating_sub(2));
+            let space = width - ansi::width(&title);
+            let before = match self.border_title_align {
+                _ if title.is_empty() => space,
+                Align::Left => space.min(1),
+                Align::Center => space / 2,
+                Align::Right => space.saturating_sub(1),
+            };
+            draw(corner_left, left)
+                + &draw(edge, before)
+                + &title
+                + &draw(edge, space - before)
+                + &draw(corner_right, right)
+        };
+
+        let mut out = vec![];
+        if sides.top {
+            out.push(edge(
+                &border.top_left,
+                &border.top,
+                &border.top_right,
+                &self.border_title,
+            ));
+        }
+        for line in block {
+            out.push(draw(&border.left, left) + &line + &draw(&border.right, right));
+        }
+        if sides.bottom {
+            out.push(edge(
+                &border.bottom_left,
+                &border.bottom,
+                &border.bottom_right,
+                "",
+            ));
+        }
+        out
+    }
+
+    // The sequence turning the style on, empty for the plain style.
+    fn open(&self) -> String {
+        let mut params = vec![];
+        let attributes = [
+            (self.bold, "1"),
+            (self.faint, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.reverse, "7"),
+            (self.strikethrough, "9"),
+        ];
+        for (on, param) in attributes.iter() {
+            if *on {
+                params.push(param.to_string());
+            }
+        }
+        let profile = profile();
+        params.extend(self.foreground.and_then(|c| c.sgr(profile, false)));
+        params.extend(self.background.and_then(|c| c.sgr(profile, true)));
+        sgr(&params)
+    }
+
+    // The sequence for the background alone, for padding.
+    fn background_open(&self) -> String {
+        let params = self.background.and_then(|c| c.sgr(profile(), true));
+        sgr(&params.into_iter().collect::<Vec<_>>())
+    }
+}
+
+fn sgr(params: &[String]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    format!("\x1B[{}m", params.join(";"))
+}
+
+/// join_horizontal puts blocks side by side, the shorter ones are aligned in
+/// the height of the tallest:
+///
+/// ```
+/// use moonlight::style::{join_horizontal, VerticalAlign};
+///
+/// let view = join_horizontal(VerticalAlign::Center, &["one\ntwo\nthree", "four"]);
+/// assert_eq!(view, "one      \ntwo  four\nthree    ");
+/// ```
+pub fn join_horizontal(align: VerticalAlign, blocks: &[&str]) -> String {
+    let blocks: Vec<Vec<String>> = blocks.iter().map(|block| lines(block)).collect();
+    let height = blocks.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut out = vec![String::new(); height];
+    for block in blocks {
+        let width = block.first().map_or(0, |line| ansi::width(line));
+        let block = align_vertically(block, height, align, width);
+        for (row, line) in out.iter_mut().zip(block) {
+            row.push_str(&line);
+        }
+    }
+    out.join("\n")
+}
+
+/// join_vertical stacks blocks, the narrower lines are aligned in the width
+/// of the widest.
+pub fn join_vertical(align: Align, blocks: &[&str]) -> String {
+    let lines: Vec<&str> = blocks.iter().flat_map(|block| block.split('\n')).collect();
+    let width = lines
+        .iter()
+        .map(|line| ansi::width(line))
+        .max()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| align_horizontally(line, width, align))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// place positions a block in an area of the given width and height, filled
+/// with spaces. Blocks larger than the area are left as they are on that
+/// axis.
+pub fn place(
+    width: usize,
+    height: usize,
+    horizontal: Align,
+    vertical: VerticalAlign,
+    block: &str,
+) -> String {
+    let block = lines(block);
+    let block_width = block.first().map_or(0, |line| ansi::width(line));
+    let width = width.max(block_width);
+    let block = block
+        .iter()
+        .map(|line| align_horizontally(line, width, horizontal))
+        .collect();
+    align_vertically(block, height, vertical, width).join("\n")
+}
+
+// The lines of a block, padded to the width of the widest.
+fn lines(block: &str) -> Vec<String> {
+    let width = block.split('\n').map(ansi::width).max().unwrap_or(0);
+    block
+        .split('\n')
+        .map(|line| align_horizontally(line, width, Align::Left))
+        .collect()
+}
+
+fn align_horizontally(line: &str, width: usize, align: Align) -> String {
+    let space = width.saturating_sub(ansi::width(line));
+    let before = match align {
+        Align::Left => 0,
+        Align::Center => space / 2,
+        Align::Right => space,
+    };
+    " ".repeat(before) + line + &" ".repeat(space - before)
+}
+
+// Add blank lines of the given width to the block, up to the height.
+fn align_vertically(
+    mut block: Vec<String>,
+    height: usize,
+    align: VerticalAlign,
+    width: usize,
+) -> Vec<String> {
+    let space = height.saturating_sub(block.len());
+    let before = match align {
+        VerticalAlign::Top => 0,
+        VerticalAlign::Center => space / 2,
+        VerticalAlign::Bottom => space,
+    };
+    let blank = " ".repeat(width);
+    let mut out = vec![blank.clone(); before];
+    out.append(&mut block);
+    out.extend(vec![blank; space - before]);
+    out
+}
+
+// Repeat s over the given width, what's left is blank.
+fn fill(s: &str, width: usize) -> String {
+    let count = match ansi::width(s) {
+        0 => 0,
+        w => width / w + 1,
+    };
+    let s = ansi::truncate(&s.repeat(count), width);
+    let used = ansi::width(&s);
+    s + &" ".repeat(width - used)
+}
+
+fn paint(s: &str, open: &str) -> String {
+    if open.is_empty() || s.is_empty() {
+        return s.to_string();
+    }
+    format!("{}{}{}", open, s, RESET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_styles_across_line_breaks() {
+        let rendered = Style::new().render("\x1B[31mred\nstill red\x1B[0m");
+        let lines: Vec<&str> = rendered.split('\n').collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("\x1B[31mred"));
+        assert!(lines[1].starts_with("\x1B[31mstill red"));
+    }
+
+    #[test]
+    fn keeps_lines_within_the_width() {
+        for width in 1..5 {
+            let rendered = Style::new().with_width(width).render("日本 語");
+            for line in rendered.split('\n') {
+                assert_eq!(ansi::width(line), width, "{:?}", rendered);
+            }
+        }
+    }
+}
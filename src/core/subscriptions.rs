@@ -0,0 +1,103 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime},
+};
+
+use crate::commands::{self, CancelHandle, Cmd};
+
+use super::executor::Context;
+
+/// Subscriptions computes the subscriptions a program needs from its current
+/// model, see `Runtime::with_subscriptions`.
+pub type Subscriptions<Model, Message> = fn(&Model) -> Vec<Sub<Message>>;
+
+/// Sub is a long running source of messages, like a timer or a listener,
+/// identified by a key. After every update the runtime compares the keys
+/// returned by the subscriptions function with the running ones: new keys are
+/// started, missing keys are stopped and the others keep running untouched.
+///
+/// Put whatever the subscription depends on in its key, e.g. the interval of
+/// a timer, so it's restarted when that changes.
+pub struct Sub<Message> {
+    key: String,
+    cmd: Cmd<Message>,
+}
+
+impl<Message: 'static> Sub<Message> {
+    /// Subscribe to the messages of a command, usually a `commands::stream`.
+    /// A stream is stopped the next time it emits after being unsubscribed.
+    pub fn new(key: impl Into<String>, cmd: Cmd<Message>) -> Self {
+        Self {
+            key: key.into(),
+            cmd,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// map converts the messages of the subscription, the key is unchanged.
+    pub fn map<O: 'static>(self, f: impl Fn(Message) -> O + Send + Sync + 'static) -> Sub<O> {
+        Sub {
+            key: self.key,
+            cmd: self.cmd.map(f),
+        }
+    }
+}
+
+/// every sends a message every time the duration elapses, aligned with the
+/// system clock like `commands::every`, until it's unsubscribed.
+pub fn every<MSG: 'static>(
+    key: impl Into<String>,
+    d: Duration,
+    fun: impl Fn(SystemTime) -> MSG + Send + 'static,
+) -> Sub<MSG> {
+    let cmd = commands::stream(move |emitter| loop {
+        commands::sleep_until_next(d);
+        if !emitter.emit(fun(SystemTime::now())) {
+            break;
+        }
+    });
+    Sub::new(key, cmd)
+}
+
+/// Running keeps track of the subscriptions started by the runtime.
+#[derive(Default)]
+pub(crate) struct Running {
+    handles: HashMap<String, CancelHandle>,
+}
+
+impl Running {
+    /// Start the subscriptions which aren't running yet and stop the ones
+    /// which are no longer wanted.
+    pub(crate) fn update<Message: Send + 'static>(
+        &mut self,
+        subs: Vec<Sub<Message>>,
+        ctx: &Context<Message>,
+    ) {
+        let keys: HashSet<String> = subs.iter().map(|sub| sub.key.clone()).collect();
+        self.handles.retain(|key, handle| {
+            let keep = keys.contains(key);
+            if !keep {
+                handle.cancel();
+            }
+            keep
+        });
+
+        for sub in subs {
+            if self.handles.contains_key(&sub.key) {
+                continue;
+            }
+            let (cmd, handle) = commands::cancellable(sub.cmd);
+            self.handles.insert(sub.key, handle);
+            ctx.execute(cmd);
+        }
+    }
+
+    pub(crate) fn stop(&mut self) {
+        for (_, handle) in self.handles.drain() {
+            handle.cancel();
+        }
+    }
+}